@@ -0,0 +1,35 @@
+//! Compare case-insensitive matching of ASCII strings with `Pattern::matches_case` and
+//! `Pattern::matches_case_ascii_only_fast`.
+//!
+//! Run with `cargo bench --bench ascii`.
+
+mod common;
+
+use common::bench;
+use moenster::{Case, Pattern};
+
+fn main() {
+    let subjects: Vec<String> = (0..1_000)
+        .map(|i| {
+            format!(
+                "Request-ID: {:08x}; Host: Example.COM; Path: /v1/items/{}",
+                i * 7919,
+                i
+            )
+        })
+        .collect();
+    let compiled = Pattern::new("request-id: *; host: example.com; path: /v1/items/1*");
+
+    bench("matches_case", || {
+        subjects
+            .iter()
+            .filter(|s| compiled.matches_case(s, Case::Insensitive))
+            .count()
+    });
+    bench("ascii_only", || {
+        subjects
+            .iter()
+            .filter(|s| compiled.matches_case_ascii_only_fast(s, Case::Insensitive) == Ok(true))
+            .count()
+    });
+}
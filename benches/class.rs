@@ -0,0 +1,29 @@
+//! Compare matching a pattern with large bracket classes with a compiled `Pattern`, which
+//! looks bytes up in a bitset, and `Pattern::matches_by`, which scans the class members.
+//!
+//! Run with `cargo bench --bench class`.
+
+mod common;
+
+use common::bench;
+use moenster::Pattern;
+
+fn main() {
+    let subjects: Vec<String> = (0..1_000)
+        .map(|i| format!("{}-session_token.{}", "abcdefghij".repeat(i % 8 + 1), i))
+        .collect();
+    let class = "[a-cd-fg-ij-lm-op-rs-uv-xyz_.0-9-]";
+    let pattern = format!("{}*{}{}{}", class.repeat(8), class, class, class);
+
+    let compiled = Pattern::new(&pattern);
+
+    bench("members", || {
+        subjects
+            .iter()
+            .filter(|s| compiled.matches_by(s, |p, c| p == c))
+            .count()
+    });
+    bench("bitset", || {
+        subjects.iter().filter(|s| compiled.matches(s)).count()
+    });
+}
@@ -0,0 +1,20 @@
+//! Timing shared by all benchmarks.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 1_000;
+
+/// Run `f` repeatedly after a warm up and print the average time per run.
+pub fn bench<F: FnMut() -> usize>(name: &str, mut f: F) {
+    // Warm up
+    for _ in 0..ITERATIONS / 10 {
+        black_box(f());
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    println!("{:<12} {:?}/iter", name, start.elapsed() / ITERATIONS);
+}
@@ -0,0 +1,28 @@
+//! Compare case-insensitive matching of a generic compiled pattern with a pre-lowered one.
+//!
+//! Run with `cargo bench --bench insensitive`.
+
+mod common;
+
+use common::bench;
+use moenster::{Case, Pattern};
+
+fn main() {
+    let subjects: Vec<String> = (0..1_000)
+        .map(|i| format!("Some-Mixed-Case-SUBJECT-{}.TXT", i))
+        .collect();
+    let pattern = "some-mixed-case-subject-[0-9]*5.txt";
+
+    let generic = Pattern::new(pattern);
+    let lowered = Pattern::new_ci(pattern);
+
+    bench("generic", || {
+        subjects
+            .iter()
+            .filter(|s| generic.matches_case(s, Case::Insensitive))
+            .count()
+    });
+    bench("new_ci", || {
+        subjects.iter().filter(|s| lowered.matches(s)).count()
+    });
+}
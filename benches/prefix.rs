@@ -0,0 +1,26 @@
+//! Compare matching a long literal prefix pattern with a compiled `Pattern`, which compares
+//! the prefix at once, and `Pattern::matches_by`, which compares it token by token.
+//!
+//! Run with `cargo bench --bench prefix`.
+
+mod common;
+
+use common::bench;
+use moenster::Pattern;
+
+fn main() {
+    let subjects: Vec<String> = (0..1_000)
+        .map(|i| format!("org/example/project/module/submodule/file-{}.rs", i))
+        .collect();
+    let compiled = Pattern::new("org/example/project/module/submodule/file-1*");
+
+    bench("tokens", || {
+        subjects
+            .iter()
+            .filter(|s| compiled.matches_by(s, |p, c| p == c))
+            .count()
+    });
+    bench("prefix", || {
+        subjects.iter().filter(|s| compiled.matches(s)).count()
+    });
+}
@@ -0,0 +1,29 @@
+//! Compare matching a `*` followed by a long literal against a long string with a compiled
+//! `Pattern`, which jumps to the literal with a skip table, and `Pattern::matches_by`, which
+//! tries every position.
+//!
+//! Run with `cargo bench --bench skip`.
+
+mod common;
+
+use common::bench;
+use moenster::Pattern;
+
+fn main() {
+    let subjects: Vec<String> = (0..100)
+        .map(|i| format!("{}verylongliteral-{}", "very long literals ".repeat(500), i))
+        .collect();
+    let pattern = "*verylongliteral-1*";
+
+    let compiled = Pattern::new(pattern);
+
+    bench("scan", || {
+        subjects
+            .iter()
+            .filter(|s| compiled.matches_by(s, |p, c| p == c))
+            .count()
+    });
+    bench("skip", || {
+        subjects.iter().filter(|s| compiled.matches(s)).count()
+    });
+}
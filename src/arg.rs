@@ -0,0 +1,76 @@
+//! Parsing patterns from command line arguments.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Pattern, PatternError};
+
+/// A compiled pattern that remembers its source, parsed with [`FromStr`].
+///
+/// Fits argument parsers like `clap`, which parse values with `FromStr` and show them with
+/// `Display`: a malformed glob is rejected while parsing the arguments, not when matching.
+///
+/// ```
+/// # use moenster::GlobArg;
+/// let filter: GlobArg = "*.rs".parse().unwrap();
+/// assert!(filter.pattern().matches("lib.rs"));
+/// assert_eq!(filter.to_string(), "*.rs");
+/// assert!("[a-z".parse::<GlobArg>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobArg {
+    source: String,
+    pattern: Pattern,
+}
+
+impl GlobArg {
+    /// The compiled pattern.
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// The pattern as it was given.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for GlobArg {
+    type Err = PatternError;
+
+    fn from_str(source: &str) -> Result<GlobArg, PatternError> {
+        Ok(GlobArg {
+            pattern: Pattern::try_new(source)?,
+            source: source.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for GlobArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        let arg: GlobArg = "src/*.r[sx]".parse().unwrap();
+        assert_eq!(arg.as_str(), "src/*.r[sx]");
+        assert_eq!(arg.to_string(), "src/*.r[sx]");
+        assert!(arg.pattern().matches("src/lib.rs"));
+        assert!(!arg.pattern().matches("lib.rs"));
+
+        assert_eq!(
+            "src/[a-z".parse::<GlobArg>(),
+            Err(PatternError::UnclosedBracket { position: 4 })
+        );
+        assert_eq!(
+            "src\\".parse::<GlobArg>(),
+            Err(PatternError::TrailingEscape)
+        );
+    }
+}
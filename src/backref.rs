@@ -0,0 +1,138 @@
+//! Matching patterns that refer back to what an earlier `*` matched.
+
+use std::ops::Range;
+
+use crate::{
+    is_boundary, repeatable_len, skip_trailing_stars, stringmatch_bytes, Case, MatchOptions,
+};
+
+/// Whether the pattern contains a reference like `\1`.
+pub(crate) fn has_backreference(pattern: &[u8]) -> bool {
+    pattern
+        .windows(2)
+        .any(|w| w[0] == b'\\' && (b'1'..=b'9').contains(&w[1]))
+}
+
+/// Match with `\1` to `\9` referring to the text matched by the first to ninth `*`.
+///
+/// See [`MatchOptions::backreferences`].
+pub(crate) fn matches_with_backreferences(
+    pattern: &[u8],
+    string: &[u8],
+    options: &MatchOptions,
+) -> bool {
+    if string.is_empty() {
+        // Nothing to capture, but the empty string matches as it always does.
+        return stringmatch_bytes(pattern, string, options, usize::MAX);
+    }
+    backref_match(pattern, string, 0, options, &mut Vec::new())
+}
+
+/// Match the pattern against the string from `pos`, with `spans` holding the text matched by
+/// each `*` so far.
+fn backref_match(
+    pattern: &[u8],
+    string: &[u8],
+    pos: usize,
+    options: &MatchOptions,
+    spans: &mut Vec<Range<usize>>,
+) -> bool {
+    let literal_star = options.double_star_literal && pattern.starts_with(b"**");
+    match pattern.first() {
+        None => pos == string.len(),
+        Some(b'*') if !literal_star => {
+            let rest = skip_trailing_stars(pattern);
+            let globstar =
+                (options.path || options.dot_is_boundary) && pattern.len() - rest.len() >= 2;
+            let mut end = pos;
+            loop {
+                spans.push(pos..end);
+                if backref_match(rest, string, end, options, spans) {
+                    return true;
+                }
+                spans.pop();
+                if end == string.len() || (!globstar && is_boundary(string[end], options)) {
+                    return false;
+                }
+                end += 1;
+            }
+        }
+        Some(b'\\') if matches!(pattern.get(1), Some(b'1'..=b'9')) => {
+            // A reference to a `*` that didn't match yet matches nothing.
+            let span = match spans.get(usize::from(pattern[1] - b'1')) {
+                Some(span) => span.clone(),
+                None => return false,
+            };
+            let captured = &string[span];
+            let end = pos + captured.len();
+            end <= string.len()
+                && if options.case == Case::Sensitive {
+                    string[pos..end] == *captured
+                } else {
+                    string[pos..end].eq_ignore_ascii_case(captured)
+                }
+                && backref_match(&pattern[2..], string, end, options, spans)
+        }
+        Some(_) => {
+            let len = if literal_star {
+                2
+            } else {
+                repeatable_len(pattern).max(1)
+            };
+            pos < string.len()
+                && stringmatch_bytes(&pattern[..len], &string[pos..=pos], options, usize::MAX)
+                && backref_match(&pattern[len..], string, pos + 1, options, spans)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{stringmatch_with_options, Case, MatchOptions};
+
+    #[test]
+    fn repeated_segments() {
+        let options = MatchOptions {
+            backreferences: true,
+            ..MatchOptions::default()
+        };
+        let matches = |pattern, string| stringmatch_with_options(pattern, string, &options);
+
+        assert!(matches("*-\\1", "ab-ab"));
+        assert!(!matches("*-\\1", "ab-cd"));
+        assert!(!matches("*-\\1", "ab-abc"));
+        assert!(matches("*-\\1", "-"));
+        assert!(matches("*/*/\\2/\\1", "a/bc/bc/a"));
+        assert!(!matches("*/*/\\2/\\1", "a/bc/a/bc"));
+        assert!(matches("<*>*</\\1>", "<b>bold</b>"));
+        assert!(!matches("<*>*</\\1>", "<b>bold</i>"));
+        assert!(matches("[a-z]?*=\\1", "xy12=12"));
+        // References to a `*` after them, or to none at all, match nothing.
+        assert!(!matches("\\1*", "aa"));
+        assert!(!matches("*\\2", "aa"));
+        // Other escapes keep their meaning.
+        assert!(matches("*\\0\\*\\1", "a0*a"));
+
+        // Without the option, `\1` is a literal `1`.
+        assert!(stringmatch_with_options(
+            "*-\\1",
+            "ab-1",
+            &MatchOptions::default()
+        ));
+        assert!(!matches("*-\\1", "ab-1"));
+
+        let insensitive = MatchOptions {
+            case: Case::Insensitive,
+            ..options.clone()
+        };
+        assert!(stringmatch_with_options("*-\\1", "ab-AB", &insensitive));
+
+        let path = MatchOptions {
+            path: true,
+            ..options
+        };
+        assert!(stringmatch_with_options("*-\\1", "a-a", &path));
+        assert!(!stringmatch_with_options("*-\\1", "a/b-a/b", &path));
+        assert!(stringmatch_with_options("**-\\1", "a/b-a/b", &path));
+    }
+}
@@ -0,0 +1,121 @@
+//! Sharing compiled patterns between threads.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use crate::Pattern;
+
+/// A cache of compiled patterns that can be shared between threads.
+///
+/// Patterns are compiled on first use and reused by later calls from any thread.
+/// The cache holds at most `capacity` patterns; once it is full, new patterns are compiled for
+/// each call without being stored, so untrusted input can't grow it without bound.
+///
+/// ```
+/// # use moenster::ConcurrentPatternCache;
+/// let cache = ConcurrentPatternCache::new(100);
+/// assert!(cache.matches("*.rs", "lib.rs"));
+/// assert!(!cache.matches("*.rs", "Cargo.toml"));
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentPatternCache {
+    patterns: RwLock<HashMap<String, Arc<Pattern>>>,
+    capacity: usize,
+}
+
+impl ConcurrentPatternCache {
+    /// Create an empty cache holding at most `capacity` patterns.
+    pub fn new(capacity: usize) -> ConcurrentPatternCache {
+        ConcurrentPatternCache {
+            patterns: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Match a string against the pattern, compiling it if it isn't cached yet.
+    pub fn matches(&self, pattern: &str, string: &str) -> bool {
+        self.get(pattern).matches(string)
+    }
+
+    /// Get the compiled pattern, compiling and caching it if needed.
+    pub fn get(&self, pattern: &str) -> Arc<Pattern> {
+        if let Some(compiled) = self.read().get(pattern) {
+            return Arc::clone(compiled);
+        }
+
+        let compiled = Arc::new(Pattern::new(pattern));
+        let mut patterns = self.patterns.write().unwrap_or_else(|e| e.into_inner());
+        // Another thread might have compiled it in the meantime.
+        if let Some(existing) = patterns.get(pattern) {
+            return Arc::clone(existing);
+        }
+        if patterns.len() < self.capacity {
+            patterns.insert(pattern.to_string(), Arc::clone(&compiled));
+        }
+        compiled
+    }
+
+    /// The number of cached patterns.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns true if no patterns are cached.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Remove all cached patterns.
+    pub fn clear(&self) {
+        self.patterns
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Arc<Pattern>>> {
+        // The map is never left half-updated, so a poisoned lock is still usable.
+        self.patterns.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn shared_between_threads() {
+        let cache = Arc::new(ConcurrentPatternCache::new(10));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let cache = Arc::clone(&cache);
+                thread::spawn(move || {
+                    for j in 0..100 {
+                        assert!(cache.matches("file[0-9]*.rs", &format!("file{}{}.rs", i, j)));
+                        assert!(!cache.matches("file[0-9]*.rs", &format!("file{}.toml", i)));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn bounded() {
+        let cache = ConcurrentPatternCache::new(2);
+        for i in 0..10 {
+            let pattern = format!("{}*", i);
+            assert!(cache.matches(&pattern, &format!("{}abc", i)));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.matches("9*", "9"));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! The parts of a string matched by the wildcards of a pattern.
+
+use std::ops::Range;
+
+/// The text matched by each `*` of a pattern, returned by [`Pattern::captures`].
+///
+/// Captures are numbered from 0 in pattern order, one for each `*`, counting a run of stars
+/// once.
+///
+/// ```
+/// # use moenster::Pattern;
+/// let captures = Pattern::new("*: *").captures("größe: 5 cm").unwrap();
+/// assert_eq!(captures.get(0), Some("größe"));
+/// assert_eq!(captures.byte_len(0), Some(7));
+/// assert_eq!(captures.char_count(0), Some(5));
+/// ```
+///
+/// [`Pattern::captures`]: crate::Pattern::captures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures<'s> {
+    string: &'s str,
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'s> Captures<'s> {
+    pub(crate) fn new(string: &'s str, ranges: Vec<Range<usize>>) -> Captures<'s> {
+        Captures { string, ranges }
+    }
+
+    /// The number of captures.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns true if the pattern has no `*`.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The byte range of the string matched by capture `i`.
+    pub fn range(&self, i: usize) -> Option<Range<usize>> {
+        self.ranges.get(i).cloned()
+    }
+
+    /// The text matched by capture `i`.
+    ///
+    /// Returns `None` as well if the capture starts or ends within a character, which can
+    /// happen as `?` matches a single byte.
+    pub fn get(&self, i: usize) -> Option<&'s str> {
+        self.string.get(self.range(i)?)
+    }
+
+    /// The length of capture `i` in bytes.
+    pub fn byte_len(&self, i: usize) -> Option<usize> {
+        self.range(i).map(|range| range.len())
+    }
+
+    /// The number of characters in capture `i`.
+    ///
+    /// Differs from [`Captures::byte_len`] for characters outside of ASCII. A character cut
+    /// in two by the capture counts once if its first byte is part of the capture.
+    pub fn char_count(&self, i: usize) -> Option<usize> {
+        let bytes = &self.string.as_bytes()[self.range(i)?];
+        // Every byte except UTF-8 continuation bytes starts a character.
+        Some(bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Pattern;
+
+    #[test]
+    fn byte_len_and_char_count() {
+        let captures = Pattern::new("*").captures("mønster").unwrap();
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures.byte_len(0), Some(8));
+        assert_eq!(captures.char_count(0), Some(7));
+
+        let captures = Pattern::new("*-*").captures("日本-abc").unwrap();
+        assert_eq!(captures.get(0), Some("日本"));
+        assert_eq!(captures.byte_len(0), Some(6));
+        assert_eq!(captures.char_count(0), Some(2));
+        assert_eq!(captures.byte_len(1), Some(3));
+        assert_eq!(captures.char_count(1), Some(3));
+        assert_eq!(captures.byte_len(2), None);
+        assert_eq!(captures.char_count(2), None);
+
+        // `?` takes the first byte of `ø`, leaving the capture to start within it.
+        let captures = Pattern::new("m?*").captures("mønster").unwrap();
+        assert_eq!(captures.get(0), None);
+        assert_eq!(captures.byte_len(0), Some(6));
+        assert_eq!(captures.char_count(0), Some(5));
+
+        assert!(Pattern::new("lib.rs")
+            .captures("lib.rs")
+            .unwrap()
+            .is_empty());
+        assert_eq!(Pattern::new("*.rs").captures("lib.md"), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Matching against already decoded characters.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Match a sequence of characters against the specified pattern.
+///
+/// Works like [`stringmatch`](crate::stringmatch), but on characters instead of bytes: `?`
+/// matches a single character and bracketed patterns compare characters, so `[α-ω]` matches
+/// any lowercase Greek letter.
+///
+/// The characters are read one at a time and never buffered, by tracking every position in the
+/// pattern the characters read so far could have reached. This takes time proportional to the
+/// pattern length for each character, whatever the pattern looks like.
+///
+/// Supported are `*`, `?`, bracketed patterns, escapes and the shorthand classes `\d`, `\w`
+/// and `\s`. Matching is always case-sensitive.
+///
+/// ```
+/// # use moenster::matches_char_iter;
+/// assert!(matches_char_iter("m?nster", "mønster".chars()));
+/// assert!(matches_char_iter("[α-ω]*", vec!['λ', 'x'].into_iter()));
+/// ```
+pub fn matches_char_iter<I: Iterator<Item = char>>(pattern: &str, chars: I) -> bool {
+    let elements = parse(pattern);
+
+    // active[i]: the characters so far can be matched up to element `i`
+    let mut active = vec![false; elements.len() + 1];
+    active[0] = true;
+    skip_stars(&elements, &mut active);
+
+    let mut empty = true;
+    for c in chars {
+        empty = false;
+        let mut next = vec![false; elements.len() + 1];
+        for (i, element) in elements.iter().enumerate() {
+            if !active[i] {
+                continue;
+            }
+            match element {
+                Element::AnyString => next[i] = true,
+                element if element.matches(c) => next[i + 1] = true,
+                _ => {}
+            }
+        }
+        skip_stars(&elements, &mut next);
+        if !next.contains(&true) {
+            return false;
+        }
+        active = next;
+    }
+
+    // Non-empty patterns never match the empty string.
+    if empty {
+        return elements.is_empty();
+    }
+    active[elements.len()]
+}
+
+/// Let every active position in front of a `*` also continue after it, as `*` can match
+/// nothing.
+fn skip_stars(elements: &[Element], active: &mut [bool]) {
+    for (i, element) in elements.iter().enumerate() {
+        if active[i] && *element == Element::AnyString {
+            active[i + 1] = true;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Element {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyString,
+    /// `[...]`, with inclusive ranges
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    /// `\d`, `\w` or `\s`
+    Shorthand(char),
+}
+
+impl Element {
+    /// Whether this element matches the single character `c`.
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Element::Literal(l) => *l == c,
+            Element::AnyChar | Element::AnyString => true,
+            Element::Class { negated, ranges } => {
+                let matched = ranges.iter().any(|&(start, end)| c >= start && c <= end);
+                matched != *negated
+            }
+            Element::Shorthand(class) => {
+                let matched = match class.to_ascii_lowercase() {
+                    'd' => c.is_ascii_digit(),
+                    'w' => c.is_ascii_alphanumeric() || c == '_',
+                    _ => c.is_ascii_whitespace(),
+                };
+                matched != class.is_ascii_uppercase()
+            }
+        }
+    }
+}
+
+/// Parse a pattern into elements, the same way the byte matchers read it.
+fn parse(pattern: &str) -> Vec<Element> {
+    let mut elements = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let element = match c {
+            '*' => Element::AnyString,
+            '?' => Element::AnyChar,
+            '[' => parse_class(&mut chars),
+            '\\' => match chars.next() {
+                Some(class @ ('d' | 'D' | 'w' | 'W' | 's' | 'S')) => Element::Shorthand(class),
+                Some(escaped) => Element::Literal(escaped),
+                None => Element::Literal('\\'),
+            },
+            c => Element::Literal(c),
+        };
+        // Consecutive stars match the same as a single one.
+        if element != Element::AnyString || elements.last() != Some(&Element::AnyString) {
+            elements.push(element);
+        }
+    }
+    elements
+}
+
+/// Parse the rest of a bracketed pattern after the `[`.
+///
+/// An unclosed bracket runs up to the end of the pattern.
+fn parse_class(chars: &mut Peekable<Chars<'_>>) -> Element {
+    let negated = chars.next_if_eq(&'^').is_some();
+    let mut ranges = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some() => {
+                let escaped = chars.next().unwrap_or(c);
+                ranges.push((escaped, escaped));
+            }
+            ']' => break,
+            start => {
+                // A range needs both ends, otherwise the `-` is a member itself.
+                let mut lookahead = chars.clone();
+                match (lookahead.next(), lookahead.next()) {
+                    (Some('-'), Some(end)) => {
+                        chars.next();
+                        chars.next();
+                        ranges.push((start.min(end), start.max(end)));
+                    }
+                    _ => ranges.push((start, start)),
+                }
+            }
+        }
+    }
+    Element::Class { negated, ranges }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stringmatch;
+
+    #[test]
+    fn same_as_stringmatch_for_ascii() {
+        let patterns = [
+            "moenster",
+            "m*r",
+            "m**r",
+            "*",
+            "m?enster",
+            "m[oei]enster",
+            "m[^oei]enster",
+            "m[n-p]enster",
+            "m[p-n]enster",
+            "m[a-]*",
+            "m[n-p",
+            "m\\*",
+            "\\d\\W*",
+            "*ster*",
+            "",
+        ];
+        let strings = [
+            "moenster", "mienster", "m*", "m-x", "mo", "7 ", "", "master", "monsters",
+        ];
+        for pattern in &patterns {
+            for string in &strings {
+                let chars: Vec<char> = string.chars().collect();
+                assert_eq!(
+                    matches_char_iter(pattern, chars.into_iter()),
+                    stringmatch(pattern, string),
+                    "{:?} against {:?}",
+                    pattern,
+                    string
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn characters() {
+        let chars: Vec<char> = "mønster".chars().collect();
+        assert!(matches_char_iter("m?nster", chars.iter().copied()));
+        assert!(matches_char_iter("m[ø]nster", chars.iter().copied()));
+        assert!(matches_char_iter("m[a-ø]nster", chars.iter().copied()));
+        assert!(!matches_char_iter("m??nster", chars.iter().copied()));
+        assert!(matches_char_iter("*[🦀]", "rust🦀".chars()));
+        assert!(!matches_char_iter("[^α-ω]", "λ".chars()));
+    }
+}
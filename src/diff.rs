@@ -0,0 +1,101 @@
+//! Comparing which strings two patterns match.
+
+use crate::Pattern;
+
+/// How many samples are generated from each pattern.
+const SAMPLE_LIMIT: usize = 1_000;
+/// How many examples are kept for each side.
+const EXAMPLE_LIMIT: usize = 5;
+
+/// Example strings showing how the match sets of two patterns differ.
+///
+/// Returned by [`pattern_diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatternDiff {
+    /// Strings matched by the first pattern, but not by the second.
+    pub only_a: Vec<String>,
+    /// Strings matched by the second pattern, but not by the first.
+    pub only_b: Vec<String>,
+}
+
+impl PatternDiff {
+    /// Returns true if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty()
+    }
+}
+
+/// Find example strings matched by one of the patterns, but not by the other.
+///
+/// Candidates are sampled from the structure of both patterns: their literals are kept and the
+/// wildcards are filled with characters either pattern mentions. This is best-effort: finding
+/// no differences doesn't prove that the patterns match the same strings.
+/// At most a handful of examples is returned for each side.
+///
+/// ```
+/// # use moenster::pattern_diff;
+/// let diff = pattern_diff("file[0-9]", "file[0-5]");
+/// assert!(diff.only_a.contains(&"file9".to_string()));
+/// assert!(diff.only_b.is_empty());
+/// ```
+pub fn pattern_diff(a: &str, b: &str) -> PatternDiff {
+    let a = Pattern::new(a);
+    let b = Pattern::new(b);
+
+    let mut alphabet = a.interesting_bytes();
+    alphabet.extend(b.interesting_bytes());
+    alphabet.push(b'x');
+    alphabet.sort_unstable();
+    alphabet.dedup();
+
+    let mut candidates: Vec<String> = a
+        .samples(&alphabet, SAMPLE_LIMIT)
+        .into_iter()
+        .chain(b.samples(&alphabet, SAMPLE_LIMIT))
+        .filter_map(|sample| String::from_utf8(sample).ok())
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let mut diff = PatternDiff::default();
+    for candidate in candidates {
+        let side = match (a.matches(&candidate), b.matches(&candidate)) {
+            (true, false) => &mut diff.only_a,
+            (false, true) => &mut diff.only_b,
+            _ => continue,
+        };
+        if side.len() < EXAMPLE_LIMIT {
+            side.push(candidate);
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_patterns() {
+        let diff = pattern_diff("file[0-9]", "file[0-5]");
+        assert!(diff.only_a.contains(&"file9".to_string()));
+        assert!(diff.only_b.is_empty());
+
+        let diff = pattern_diff("*.rs", "*.r?");
+        assert!(diff.only_a.is_empty());
+        assert!(!diff.only_b.is_empty());
+        for example in &diff.only_b {
+            assert!(!example.ends_with(".rs"), "{:?}", example);
+        }
+
+        let diff = pattern_diff("src/*", "*.rs");
+        assert!(!diff.only_a.is_empty());
+        assert!(!diff.only_b.is_empty());
+    }
+
+    #[test]
+    fn same_patterns() {
+        assert!(pattern_diff("m*r", "m*r").is_empty());
+        assert!(pattern_diff("m**r", "m*r").is_empty());
+    }
+}
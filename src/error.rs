@@ -0,0 +1,119 @@
+//! Errors reported when compiling patterns.
+
+use std::error::Error;
+use std::fmt;
+use std::str::Utf8Error;
+
+/// An error found while validating a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatternError {
+    /// A `[` without a matching `]`.
+    UnclosedBracket {
+        /// Byte offset of the opening `[`.
+        position: usize,
+    },
+    /// A `\` at the very end of the pattern, with nothing to escape.
+    TrailingEscape,
+    /// An empty bracket `[]`, which can never match anything.
+    ///
+    /// Only reported by [`Pattern::try_new_strict`](crate::Pattern::try_new_strict).
+    EmptyBracket {
+        /// Byte offset of the opening `[`.
+        position: usize,
+    },
+    /// A bound of a numeric range `[#lo-hi]` too large for a `u64`.
+    ///
+    /// Only reported by [`try_stringmatch_version`](crate::try_stringmatch_version).
+    NumericBoundOutOfRange {
+        /// Byte offset of the bound.
+        position: usize,
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::UnclosedBracket { position } => {
+                write!(f, "unclosed bracket starting at byte {}", position)
+            }
+            PatternError::TrailingEscape => write!(f, "trailing escape character"),
+            PatternError::EmptyBracket { position } => {
+                write!(f, "empty bracket at byte {}", position)
+            }
+            PatternError::NumericBoundOutOfRange { position } => {
+                write!(f, "numeric bound at byte {} is out of range", position)
+            }
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+/// An error returned when compiling a pattern from bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromBytesError {
+    /// The bytes are not valid UTF-8.
+    Utf8(Utf8Error),
+    /// The pattern is malformed.
+    Pattern(PatternError),
+}
+
+impl fmt::Display for FromBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromBytesError::Utf8(err) => write!(f, "invalid UTF-8 in pattern: {}", err),
+            FromBytesError::Pattern(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for FromBytesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FromBytesError::Utf8(err) => Some(err),
+            FromBytesError::Pattern(err) => Some(err),
+        }
+    }
+}
+
+impl From<Utf8Error> for FromBytesError {
+    fn from(err: Utf8Error) -> Self {
+        FromBytesError::Utf8(err)
+    }
+}
+
+impl From<PatternError> for FromBytesError {
+    fn from(err: PatternError) -> Self {
+        FromBytesError::Pattern(err)
+    }
+}
+
+/// Matching needed more nested `*` backtracking than allowed.
+///
+/// Returned by [`Pattern::matches_with_limit`](crate::Pattern::matches_with_limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthExceeded;
+
+impl fmt::Display for DepthExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "maximum wildcard nesting depth exceeded")
+    }
+}
+
+impl Error for DepthExceeded {}
+
+/// The pattern or the string to match contained a non-ASCII byte.
+///
+/// Returned by
+/// [`Pattern::matches_case_ascii_only_fast`](crate::Pattern::matches_case_ascii_only_fast).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotAscii;
+
+impl fmt::Display for NotAscii {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pattern or string contains non-ASCII characters")
+    }
+}
+
+impl Error for NotAscii {}
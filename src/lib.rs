@@ -11,9 +11,15 @@
 //! | \[a-z]   | matches one character from the range given in the bracket | range taken from their byte values |
 //! | \[^abc]  | matches one character that is not given in the bracket | taken as byte values |
 //! | \[^a-z]  | matches one character that is not from the range given in the bracket | range taken from their byte values |
+//! | \\d      | matches one ASCII digit | `\D` matches one character that is not |
+//! | \\w      | matches one ASCII letter, digit or `_` | `\W` matches one character that is not |
+//! | \\s      | matches one ASCII whitespace character | `\S` matches one character that is not |
 //!
 //! _Note: An empty bracket can never match anything._
 //!
+//! A `\` escapes the following character, so it is matched literally.
+//! The shorthands `\d`, `\w`, `\s` and their uppercase forms are the exception.
+//!
 //! # Example
 //!
 //! ```
@@ -26,51 +32,767 @@
 #![warn(missing_docs, future_incompatible, unreachable_pub, rust_2018_idioms)]
 #![allow(clippy::collapsible_if)]
 
+mod arg;
+mod backref;
+mod cache;
+mod captures;
+mod chars;
+mod diff;
+mod error;
+mod list;
+mod pattern;
+mod record;
+mod set;
+mod stream;
+mod version;
+mod walk;
+
+pub use arg::GlobArg;
+pub use cache::ConcurrentPatternCache;
+pub use captures::Captures;
+pub use chars::matches_char_iter;
+pub use diff::{pattern_diff, PatternDiff};
+pub use error::{DepthExceeded, FromBytesError, NotAscii, PatternError};
+pub use list::matches_list_element;
+pub use pattern::{MatchProgress, MatchState, NegatedPattern, Pattern, PatternBuilder, TokenKind};
+pub use record::stringmatch_lenprefixed;
+pub use set::{Matcher, PatternSet};
+pub use stream::StreamScanner;
+pub use version::{stringmatch_version, try_stringmatch_version};
+pub use walk::walk_matching;
+
+use std::iter;
+use std::ops::Range;
+
 /// Match a string against the specified pattern.
 ///
 /// Returns true if the string matches against the pattern from start to finish.
 /// See the top-level documentation for allowed wildcards.
 pub fn stringmatch(pattern: &str, string: &str) -> bool {
-    stringmatch_bytes(pattern.as_bytes(), string.as_bytes(), Case::Sensitive)
+    stringmatch_bytes(
+        pattern.as_bytes(),
+        string.as_bytes(),
+        &MatchOptions::default(),
+        usize::MAX,
+    )
+}
+
+/// Match a string against the specified pattern, limiting how much the wildcards may consume.
+///
+/// Works like [`stringmatch`], but the total number of bytes matched by all `*` wildcards in the
+/// pattern combined must not exceed `budget`.
+///
+/// ```
+/// # use moenster::matches_with_wildcard_budget;
+/// assert!(matches_with_wildcard_budget("*-*", "ab-cd", 4));
+/// assert!(!matches_with_wildcard_budget("*-*", "ab-cd", 3));
+/// ```
+pub fn matches_with_wildcard_budget(pattern: &str, string: &str, budget: usize) -> bool {
+    stringmatch_bytes(
+        pattern.as_bytes(),
+        string.as_bytes(),
+        &MatchOptions::default(),
+        budget,
+    )
+}
+
+/// Match the part of a string starting at `offset` against the specified pattern.
+///
+/// Works like `stringmatch(pattern, &string[offset..])`, but never panics:
+/// `offset` is a byte offset and doesn't need to be on a character boundary.
+/// An `offset` at or past the end of the string matches against the empty string.
+///
+/// ```
+/// # use moenster::stringmatch_from;
+/// assert!(stringmatch_from("n*r", "mønster", 3));
+/// ```
+pub fn stringmatch_from(pattern: &str, string: &str, offset: usize) -> bool {
+    let string = string.as_bytes();
+    let offset = offset.min(string.len());
+    stringmatch_bytes(
+        pattern.as_bytes(),
+        &string[offset..],
+        &MatchOptions::default(),
+        usize::MAX,
+    )
+}
+
+/// Match a string against the specified pattern, with the given case sensitivity.
+///
+/// Works like [`stringmatch`], but with [`Case::Insensitive`] ASCII letters are compared
+/// regardless of their case.
+///
+/// ```
+/// # use moenster::{stringmatch_with_case, Case};
+/// assert!(stringmatch_with_case("M*NSTER", "mønster", Case::Insensitive));
+/// ```
+pub fn stringmatch_with_case(pattern: &str, string: &str, case: Case) -> bool {
+    let options = MatchOptions {
+        case,
+        ..MatchOptions::default()
+    };
+    stringmatch_with_options(pattern, string, &options)
 }
 
-// FIXME: Remove dead_code allowance.
-#[allow(dead_code)]
-#[derive(Copy, Clone)]
-enum Case {
+/// Match a string case-insensitively against any of the specified patterns.
+///
+/// Each pattern is compiled into its case-insensitive form (see [`Pattern::new_ci`]) once and
+/// the patterns are tried in order until one matches.
+///
+/// ```
+/// # use moenster::matches_any_ci;
+/// assert!(matches_any_ci(&["*.RS", "*.TOML"], "Cargo.toml"));
+/// ```
+pub fn matches_any_ci(patterns: &[&str], string: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| Pattern::new_ci(pattern).matches(string))
+}
+
+/// Escape the characters `,{}()|` in a pattern, so it can be embedded in a larger pattern
+/// grouping alternatives.
+///
+/// The escaped pattern matches the same strings as the original one: wildcards keep working,
+/// already escaped characters and bracketed patterns are copied unchanged.
+///
+/// ```
+/// # use moenster::{escape_for_group, stringmatch};
+/// let escaped = escape_for_group("a,b*");
+/// assert_eq!(escaped, "a\\,b*");
+/// assert!(stringmatch(&escaped, "a,bc"));
+/// ```
+pub fn escape_for_group(pattern: &str) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    let mut in_bracket = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                escaped.extend(chars.next());
+                continue;
+            }
+            '[' if !in_bracket => in_bracket = true,
+            ']' if in_bracket => in_bracket = false,
+            ',' | '{' | '}' | '(' | ')' | '|' if !in_bracket => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Match a string against the specified pattern, treating malformed patterns as literal text.
+///
+/// Well-formed patterns match like with [`stringmatch`]. Patterns rejected by
+/// [`Pattern::try_new`], like `[abc` with an unclosed bracket, only match a string equal to
+/// the pattern itself.
+///
+/// ```
+/// # use moenster::matches_lenient;
+/// assert!(matches_lenient("m*r", "mønster"));
+/// assert!(matches_lenient("m[n-p", "m[n-p"));
+/// assert!(!matches_lenient("m[n-p", "mo"));
+/// ```
+pub fn matches_lenient(pattern: &str, string: &str) -> bool {
+    match Pattern::try_new(pattern) {
+        Ok(compiled) => compiled.matches(string),
+        Err(_) => pattern == string,
+    }
+}
+
+/// How many leading bytes of the pattern are satisfied by a partially typed string.
+///
+/// Returns the length of the longest prefix of the pattern, made of whole elements, that
+/// matches all of `partial`. A `*` is satisfied as soon as it is reached, even before any of
+/// its characters are typed, and keeps absorbing input that the rest of the pattern can't
+/// match. Returns 0 if no prefix matches, for example once the input diverged from a literal.
+///
+/// ```
+/// # use moenster::longest_matching_prefix_len;
+/// assert_eq!(longest_matching_prefix_len("ab*cd", "a"), 1);
+/// assert_eq!(longest_matching_prefix_len("ab*cd", "ab"), 3);
+/// assert_eq!(longest_matching_prefix_len("ab*cd", "abxc"), 4);
+/// ```
+pub fn longest_matching_prefix_len(pattern: &str, partial: &str) -> usize {
+    let (pattern, partial) = (pattern.as_bytes(), partial.as_bytes());
+    let mut ends = vec![0];
+    while let Some(&end) = ends.last() {
+        if end >= pattern.len() {
+            break;
+        }
+        ends.push(end + repeatable_len(&pattern[end..]).max(1));
+    }
+
+    ends.into_iter()
+        .rev()
+        .find(|&end| {
+            rest_matches(
+                &pattern[..end],
+                partial,
+                &MatchOptions::default(),
+                usize::MAX,
+            )
+        })
+        .unwrap_or(0)
+}
+
+/// Whether a match is already decided, returned by [`match_completeness`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Completeness {
+    /// The string matches the pattern or can't match it anymore, whatever follows.
+    Complete(bool),
+    /// The string doesn't match yet, but continuing it can still produce a match.
+    Incomplete,
+}
+
+/// Match a string that might be cut short, like input that is still being typed.
+///
+/// Returns [`Completeness::Incomplete`] instead of a mismatch if the string is the start of
+/// some matching string, because it ran out before the pattern did.
+///
+/// ```
+/// # use moenster::{match_completeness, Completeness};
+/// assert_eq!(match_completeness("ab?d", "abcd"), Completeness::Complete(true));
+/// assert_eq!(match_completeness("ab?d", "abc"), Completeness::Incomplete);
+/// assert_eq!(match_completeness("ab?d", "abx"), Completeness::Incomplete);
+/// assert_eq!(match_completeness("ab?d", "axb"), Completeness::Complete(false));
+/// ```
+pub fn match_completeness(pattern: &str, string: &str) -> Completeness {
+    if stringmatch(pattern, string) {
+        return Completeness::Complete(true);
+    }
+
+    let bytes = pattern.as_bytes();
+    let mut end = 0;
+    loop {
+        if rest_matches(
+            &bytes[..end],
+            string.as_bytes(),
+            &MatchOptions::default(),
+            usize::MAX,
+        ) && !Pattern::from_bytes_lenient(&bytes[end..]).is_unsatisfiable()
+        {
+            return Completeness::Incomplete;
+        }
+        if end >= bytes.len() {
+            return Completeness::Complete(false);
+        }
+        end += repeatable_len(&bytes[end..]).max(1);
+    }
+}
+
+/// Check that a string contains every search term of a query, ignoring case.
+///
+/// The query is split into terms at whitespace; put a term in double quotes to keep its
+/// whitespace, like `"foo bar"`. Terms are matched as literal text, so `*` or `?` in a term
+/// match themselves. A query without terms matches every string.
+///
+/// ```
+/// # use moenster::search;
+/// assert!(search("foo bar", "Bar of FOO"));
+/// assert!(!search("\"foo bar\"", "Bar of FOO"));
+/// assert!(search("\"foo bar\"", "the foo bar"));
+/// ```
+pub fn search(query: &str, string: &str) -> bool {
+    search_terms(query).iter().all(|term| {
+        let mut pattern = String::from("*");
+        for c in term.chars() {
+            if matches!(c, '*' | '?' | '[' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+        Pattern::new_ci(&pattern).matches(string)
+    })
+}
+
+/// Split a search query into terms, at whitespace outside of double quotes.
+fn search_terms(query: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        let (term, after) = match rest.strip_prefix('"') {
+            // An unclosed quote runs to the end of the query.
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        if !term.is_empty() {
+            terms.push(term);
+        }
+        rest = after.trim_start();
+    }
+    terms
+}
+
+/// Match every `sep`-delimited segment of a string against the specified pattern.
+///
+/// Returns true only if all segments match. Empty segments need to match too, so a string
+/// ending with `sep` only matches patterns that match the empty string.
+///
+/// ```
+/// # use moenster::matches_all_segments;
+/// assert!(matches_all_segments("[a-z]*", "ab,cd,ef", ','));
+/// assert!(!matches_all_segments("[a-z]*", "ab,12,ef", ','));
+/// ```
+pub fn matches_all_segments(pattern: &str, string: &str, sep: char) -> bool {
+    let pattern = Pattern::new(pattern);
+    string.split(sep).all(|segment| pattern.matches(segment))
+}
+
+/// Check a pattern against sample strings it's expected to match.
+///
+/// Returns the indices of all samples the pattern does not match, in order, so an empty result
+/// means the pattern covers every sample.
+///
+/// ```
+/// # use moenster::validate_against;
+/// assert_eq!(validate_against("*.rs", &["lib.rs", "Cargo.toml", "main.rs"]), vec![1]);
+/// ```
+pub fn validate_against(pattern: &str, samples: &[&str]) -> Vec<usize> {
+    let pattern = Pattern::new(pattern);
+    samples
+        .iter()
+        .enumerate()
+        .filter(|(_, sample)| !pattern.matches(sample))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Match a string against the specified pattern, tolerating some mismatching characters.
+///
+/// Works like [`stringmatch`], but up to `max_mismatches` literal characters or bracketed
+/// patterns may fail to match their character in the string, like a Hamming distance.
+/// The lengths still need to line up: a mismatch replaces a character, it never inserts or
+/// removes one.
+///
+/// ```
+/// # use moenster::matches_with_tolerance;
+/// assert!(matches_with_tolerance("moenster", "mounster", 1));
+/// assert!(!matches_with_tolerance("moenster", "mounstor", 1));
+/// ```
+pub fn matches_with_tolerance(pattern: &str, string: &str, max_mismatches: usize) -> bool {
+    Pattern::new(pattern).matches_with_tolerance(string.as_bytes(), max_mismatches)
+}
+
+/// Extract the part of a string between a matching prefix and a matching suffix.
+///
+/// Looks for the shortest start of the string matching `prefix_pattern` and the shortest end of
+/// the string matching `suffix_pattern`, and returns what is in between.
+/// Returns `None` if either doesn't match or if they overlap.
+///
+/// ```
+/// # use moenster::match_between;
+/// assert_eq!(match_between("user=", ";", "user=42;"), Some("42"));
+/// assert_eq!(match_between("*=", ";", "id=7;"), Some("7"));
+/// ```
+pub fn match_between<'a>(
+    prefix_pattern: &str,
+    suffix_pattern: &str,
+    string: &'a str,
+) -> Option<&'a str> {
+    let boundaries = || {
+        string
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(string.len()))
+    };
+
+    let start = boundaries().find(|&i| stringmatch(prefix_pattern, &string[..i]))?;
+    let end = boundaries()
+        .rev()
+        .find(|&i| stringmatch(suffix_pattern, &string[i..]))?;
+    if start > end {
+        return None;
+    }
+    Some(&string[start..end])
+}
+
+/// Find the first line of a text starting with a match of the pattern.
+///
+/// Matches are only tried at the start of each line, after a `\n` or at the start of the text,
+/// and never extend past the end of their line. Returns the byte range of the longest match in
+/// the first line that has one.
+///
+/// ```
+/// # use moenster::find_line_anchored;
+/// let text = "info: started\nwarn: low disk\nerror: disk full\n";
+/// assert_eq!(find_line_anchored("error: *", text), Some(29..45));
+/// assert_eq!(find_line_anchored("disk*", text), None);
+/// ```
+pub fn find_line_anchored(pattern: &str, text: &str) -> Option<Range<usize>> {
+    let pattern = Pattern::new(pattern);
+    let mut start = 0;
+    for line in text.split('\n') {
+        let line_start = start;
+        start += line.len() + 1;
+
+        // Longest first, down to the empty match at the start of the line.
+        let found = line
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .rev()
+            .chain(iter::once(0))
+            .find(|&end| pattern.matches(&line[..end]));
+        if let Some(end) = found {
+            return Some(line_start..line_start + end);
+        }
+    }
+    None
+}
+
+/// Match a string and strip the literal text the pattern starts and ends with.
+///
+/// Returns `None` if the pattern doesn't match. Otherwise returns what is left of the string
+/// without the literals before the first and after the last wildcard or bracketed pattern,
+/// which is empty for a pattern made of literals only.
+///
+/// ```
+/// # use moenster::trim_matching;
+/// assert_eq!(trim_matching("log-*.txt", "log-abc.txt"), Some("abc"));
+/// assert_eq!(trim_matching("log-*.txt", "log-abc.md"), None);
+/// ```
+pub fn trim_matching<'a>(pattern: &str, string: &'a str) -> Option<&'a str> {
+    let range = Pattern::new(pattern).trimmed(string.as_bytes())?;
+    string.get(range)
+}
+
+/// The most assignments [`all_captures`] returns.
+const ALL_CAPTURES_LIMIT: usize = 1_000;
+
+/// Every way the `*` wildcards of the pattern can split a matching string.
+///
+/// Each assignment lists the text matched by each `*`, in pattern order; a run of stars
+/// counts as one. Assignments are ordered by the first `*` matching as little as possible,
+/// then the second and so on, and each occurs once. A pattern without `*` has one empty
+/// assignment if it matches. Returns an empty list if the pattern doesn't match.
+///
+/// The number of assignments grows quickly with the number of stars, so at most the first
+/// 1000 are returned. As `?` matches a single byte, a `*` may match part of a character,
+/// which is replaced with `U+FFFD`.
+///
+/// ```
+/// # use moenster::all_captures;
+/// assert_eq!(
+///     all_captures("*a*", "aaa"),
+///     vec![vec!["", "aa"], vec!["a", "a"], vec!["aa", ""]]
+/// );
+/// ```
+pub fn all_captures(pattern: &str, string: &str) -> Vec<Vec<String>> {
+    Pattern::new(pattern)
+        .all_captures(string.as_bytes(), ALL_CAPTURES_LIMIT)
+        .into_iter()
+        .map(|spans| {
+            spans
+                .into_iter()
+                .map(|span| String::from_utf8_lossy(&string.as_bytes()[span]).into_owned())
+                .collect()
+        })
+        .collect()
+}
+
+/// Split a string into the spans matched by each element of the pattern.
+///
+/// Returns `None` if the pattern doesn't match. Runs of literals form a single span; every
+/// `?`, `*` and bracketed pattern gets its own span, which is empty for a `*` matching
+/// nothing. The spans cover the whole string in order. Each `*` matches as little as it can.
+///
+/// ```
+/// # use moenster::{tokenize, TokenKind};
+/// assert_eq!(
+///     tokenize("id-*.?", "id-42.x"),
+///     Some(vec![
+///         (TokenKind::Literal, 0..3),
+///         (TokenKind::AnyString, 3..5),
+///         (TokenKind::Literal, 5..6),
+///         (TokenKind::AnyChar, 6..7),
+///     ])
+/// );
+/// ```
+pub fn tokenize(pattern: &str, string: &str) -> Option<Vec<(TokenKind, Range<usize>)>> {
+    Pattern::new(pattern).tokenize(string.as_bytes())
+}
+
+/// Count the bytes of the string matched by `*` wildcards.
+///
+/// Returns `None` if the pattern doesn't match. Every other element of the pattern matches
+/// exactly one byte, so the count doesn't depend on how the bytes are split among several
+/// `*`. Lower counts mean tighter matches.
+///
+/// ```
+/// # use moenster::wildcard_consumption;
+/// assert_eq!(wildcard_consumption("m?nster", "monster"), Some(0));
+/// assert_eq!(wildcard_consumption("m*r", "monster"), Some(5));
+/// assert_eq!(wildcard_consumption("m*r", "monsters"), None);
+/// ```
+pub fn wildcard_consumption(pattern: &str, string: &str) -> Option<usize> {
+    Pattern::new(pattern).wildcard_consumption(string.as_bytes())
+}
+
+/// Find the element of the pattern at which matching the string finally failed.
+///
+/// Returns `None` if the pattern matches. Otherwise returns the index of the furthest element
+/// of the pattern that couldn't be matched, across all positions tried for `*` wildcards.
+/// Elements are single characters, `?`, bracketed patterns and runs of `*`, which count as a
+/// single element. An index one past the last element means the string is too long.
+///
+/// ```
+/// # use moenster::first_unsatisfiable_token;
+/// assert_eq!(first_unsatisfiable_token("m[aeiou]nster", "mxnster"), Some(1));
+/// assert_eq!(first_unsatisfiable_token("m[aeiou]nster", "monster"), None);
+/// ```
+pub fn first_unsatisfiable_token(pattern: &str, string: &str) -> Option<usize> {
+    Pattern::new(pattern).first_unsatisfiable_token(string.as_bytes())
+}
+
+/// Find the patterns that are shadowed by an earlier pattern in the list.
+///
+/// Returns the indices of all patterns for which an earlier pattern matches every string they
+/// match, so they can never be the first one to match.
+/// Uses [`Pattern::subsumes`] and is conservative the same way.
+///
+/// ```
+/// # use moenster::find_shadowed;
+/// assert_eq!(find_shadowed(&["*.rs", "README.md", "src/*.rs"]), vec![2]);
+/// ```
+pub fn find_shadowed(patterns: &[&str]) -> Vec<usize> {
+    let compiled: Vec<_> = patterns.iter().map(|p| Pattern::new(p)).collect();
+    (0..compiled.len())
+        .filter(|&i| compiled[..i].iter().any(|p| p.subsumes(&compiled[i])))
+        .collect()
+}
+
+/// Match a path against the specified pattern.
+///
+/// Works like [`stringmatch`], but treats `/` as a path separator:
+///
+/// * `*`, `?` and bracketed patterns never match a `/`.
+/// * `**` matches any number of characters, including `/`.
+///   `**/` also matches no directory at all, so `a/**/b` matches `a/b`.
+/// * A pattern ending with `/` only matches directories, written with a trailing `/`:
+///   `logs/` matches `logs/`, but not `logs`. A trailing `**` still matches everything
+///   below a directory, so `logs/**` matches `logs/app.log` as well as `logs/` itself,
+///   while `logs/**/` matches only the directories below it.
+///
+/// ```
+/// # use moenster::stringmatch_path;
+/// assert!(stringmatch_path("src/*.rs", "src/lib.rs"));
+/// assert!(!stringmatch_path("src/*.rs", "src/bin/main.rs"));
+/// assert!(stringmatch_path("src/**/*.rs", "src/bin/main.rs"));
+/// ```
+pub fn stringmatch_path(pattern: &str, path: &str) -> bool {
+    let options = MatchOptions {
+        path: true,
+        ..MatchOptions::default()
+    };
+    stringmatch_with_options(pattern, path, &options)
+}
+
+/// Match a string against the specified pattern, with the given options.
+///
+/// ```
+/// # use moenster::{stringmatch_with_options, MatchOptions};
+/// let options = MatchOptions {
+///     optional_trailing_any: true,
+///     ..MatchOptions::default()
+/// };
+/// assert!(stringmatch_with_options("abc?", "abc", &options));
+/// assert!(stringmatch_with_options("abc?", "abcd", &options));
+/// ```
+pub fn stringmatch_with_options(pattern: &str, string: &str, options: &MatchOptions) -> bool {
+    let collapsed;
+    let string = if options.collapse_whitespace {
+        collapsed = collapse_whitespace(string.as_bytes());
+        &collapsed[..]
+    } else {
+        string.as_bytes()
+    };
+
+    let pattern = pattern.as_bytes();
+    if options.backreferences && backref::has_backreference(pattern) {
+        return backref::matches_with_backreferences(pattern, string, options);
+    }
+    stringmatch_bytes(pattern, string, options, usize::MAX)
+}
+
+/// Replace every run of ASCII whitespace with a single space.
+fn collapse_whitespace(string: &[u8]) -> Vec<u8> {
+    let mut collapsed = Vec::with_capacity(string.len());
+    for &c in string {
+        if c.is_ascii_whitespace() {
+            if collapsed.last() != Some(&b' ') {
+                collapsed.push(b' ');
+            }
+        } else {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// Case sensitivity used when matching.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Case {
+    /// Characters need to match exactly.
+    #[default]
     Sensitive,
+    /// ASCII characters are compared case-insensitively.
     Insensitive,
 }
 
-fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool {
+/// Options to adjust how patterns are matched.
+///
+/// The defaults match the same as [`stringmatch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// Case sensitivity, see [`stringmatch_with_case`].
+    pub case: Case,
+    /// Treat `/` as a path separator, see [`stringmatch_path`].
+    pub path: bool,
+    /// Let a `?` at the very end of the pattern also match nothing.
+    ///
+    /// Only the last element of the pattern is affected, so with this `abc?` matches both `abc`
+    /// and `abcd`, but `ab?c` still needs a character between `b` and `c`.
+    pub optional_trailing_any: bool,
+    /// Let `{{}}` match a balanced span of brackets.
+    ///
+    /// The span has to start with `(` or `[` and runs up to its matching `)` or `]`, so `{{}}`
+    /// matches `(a(b)c)` or `[a(b)]`, but not `(a]` or `(a`.
+    /// Write `\{{}}` to match the literal text instead.
+    pub balanced_groups: bool,
+    /// Collapse every run of ASCII whitespace in the string to a single space before matching.
+    ///
+    /// With this `a b` matches `a    b` or `a\t\nb`. Runs at the start and end of the string
+    /// are collapsed too, not removed. The string is copied to do so, which allocates.
+    pub collapse_whitespace: bool,
+    /// Let `+` repeat the element before it one or more times.
+    ///
+    /// The element can be a literal, `?`, a bracketed pattern or a shorthand class, so `a+`
+    /// matches `a` and `aaa`, and `[0-9]+` matches any run of digits. Like `?`, a literal is a
+    /// single byte. Write `\+` to match a literal `+`.
+    pub plus_quantifier: bool,
+    /// Let a space match any run of one or more ASCII whitespace characters.
+    ///
+    /// With this `a b` matches `a   b` and `a\tb`, but not `ab`. Write `\ ` to match exactly
+    /// one space.
+    pub flexible_space: bool,
+    /// Treat `.` as a boundary that wildcards don't match, like `/` in path mode.
+    ///
+    /// With this `*.example.com` matches `www.example.com`, but not `a.b.example.com`, as
+    /// neither `*`, `?` nor bracketed patterns match a `.`. Use `**` to match across dots.
+    pub dot_is_boundary: bool,
+    /// Read `**` as a literal `*`, as some dialects escape stars by doubling them.
+    ///
+    /// With this `a**b` matches `a*b` only, and `***` is a literal `*` followed by a wildcard.
+    /// This takes precedence over path mode, where `**` normally matches across `/`: with
+    /// both options there is no way to match across directories.
+    pub double_star_literal: bool,
+    /// Restrict `?` to the given bytes.
+    ///
+    /// With `Some(b"0123456789".to_vec())`, `?` only matches a digit. Boundaries from path mode
+    /// or `dot_is_boundary` are still never matched, even if they are in the set.
+    pub any_allows: Option<Vec<u8>>,
+    /// Leave out the end of ranges in bracketed patterns, so `[a-d]` matches `a`, `b` and `c`.
+    ///
+    /// Unlike any other glob dialect, ranges become half-open, like Rust's `a..d`. The larger
+    /// bound is left out, also for reversed ranges like `[d-a]`, and `[a-a]` matches nothing.
+    pub range_exclusive_end: bool,
+    /// Let `\1` to `\9` match the same text as the first to ninth `*` of the pattern.
+    ///
+    /// With this `*-\1` matches `ab-ab`, but not `ab-cd`. A reference to a `*` that comes
+    /// later in the pattern matches nothing. Patterns with references are matched by trying
+    /// every way to split the string among the stars, which gets slow for many stars on long
+    /// strings. `optional_trailing_any`, `balanced_groups`, `plus_quantifier` and
+    /// `flexible_space` don't apply to patterns with references, as they let an element match
+    /// other than exactly one byte.
+    pub backreferences: bool,
+}
+
+fn stringmatch_bytes(
+    mut pattern: &[u8],
+    mut string: &[u8],
+    options: &MatchOptions,
+    budget: usize,
+) -> bool {
+    let case = options.case;
+
     while !pattern.is_empty() && !string.is_empty() {
+        if options.plus_quantifier {
+            let len = repeatable_len(pattern);
+            if len > 0 && pattern.get(len) == Some(&b'+') {
+                let (element, rest) = (&pattern[..len], &pattern[len + 1..]);
+                let repeats = string
+                    .iter()
+                    .take_while(|&&c| stringmatch_bytes(element, &[c], options, budget))
+                    .count();
+                return (1..=repeats)
+                    .rev()
+                    .any(|n| rest_matches(rest, &string[n..], options, budget));
+            }
+        }
+
         match pattern[0] {
+            // a literal `*`, written as `**`
+            b'*' if options.double_star_literal && pattern.get(1) == Some(&b'*') => {
+                if string[0] != b'*' {
+                    return false;
+                }
+                pattern = &pattern[1..];
+                string = &string[1..];
+            }
             // any number of any characters
             b'*' => {
-                while pattern.len() > 2 && pattern[1] == b'*' {
-                    pattern = &pattern[1..];
+                let rest = skip_trailing_stars(pattern);
+                // `**` crosses path separators in path mode, and dots with `dot_is_boundary`
+                let globstar =
+                    (options.path || options.dot_is_boundary) && pattern.len() - rest.len() >= 2;
+                if rest.is_empty() {
+                    let crosses_separator =
+                        !globstar && string.iter().any(|&c| is_boundary(c, options));
+                    return string.len() <= budget && !crosses_separator;
                 }
-                if pattern.len() == 1 {
-                    return true;
+
+                if options.path && globstar && rest[0] == b'/' {
+                    // `**/` matching no directory at all
+                    if stringmatch_bytes(&rest[1..], string, options, budget) {
+                        return true;
+                    }
                 }
 
-                while !string.is_empty() {
-                    if stringmatch_bytes(&pattern[1..], string, case) {
+                let mut consumed = 0;
+                while !string.is_empty() && consumed <= budget {
+                    if stringmatch_bytes(rest, string, options, budget - consumed) {
                         return true;
                     }
+                    if !globstar && is_boundary(string[0], options) {
+                        break;
+                    }
                     string = &string[1..];
+                    consumed += 1;
                 }
 
                 return false;
             }
             // any single character
             b'?' => {
+                if is_boundary(string[0], options) {
+                    return false;
+                }
+                if let Some(allowed) = &options.any_allows {
+                    if !allowed.contains(&string[0]) {
+                        return false;
+                    }
+                }
                 string = &string[1..];
             }
             // bracketed patterns such as `[abc]` or `[a-z]`
             b'[' => {
+                if is_boundary(string[0], options) {
+                    return false;
+                }
                 pattern = &pattern[1..];
-                let not = pattern[0] == b'^';
+                let not = !pattern.is_empty() && pattern[0] == b'^';
                 if not {
                     pattern = &pattern[1..];
                 }
@@ -81,8 +803,14 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
                     } else if pattern[0] == b'\\' && pattern.len() >= 2 {
                         pattern = &pattern[1..];
 
-                        if pattern[0] == string[0] {
-                            matched = true;
+                        if matches!(case, Case::Sensitive) {
+                            if pattern[0] == string[0] {
+                                matched = true;
+                            }
+                        } else {
+                            if pattern[0].eq_ignore_ascii_case(&string[0]) {
+                                matched = true;
+                            }
                         }
                     } else if pattern[0] == b']' {
                         break;
@@ -101,7 +829,12 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
                         }
 
                         pattern = &pattern[2..];
-                        if c >= start && c <= end {
+                        let below_end = if options.range_exclusive_end {
+                            c < end
+                        } else {
+                            c <= end
+                        };
+                        if c >= start && below_end {
                             matched = true;
                         }
                     } else {
@@ -110,7 +843,7 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
                                 matched = true;
                             }
                         } else {
-                            if pattern[0].to_ascii_lowercase() != string[0].to_ascii_lowercase() {
+                            if pattern[0].eq_ignore_ascii_case(&string[0]) {
                                 matched = true;
                             }
                         }
@@ -128,6 +861,35 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
 
                 string = &string[1..];
             }
+            // a balanced span of brackets
+            b'{' if options.balanced_groups && pattern.starts_with(b"{{}}") => {
+                match balanced_span_len(string) {
+                    Some(len) => string = &string[len..],
+                    None => return false,
+                }
+                pattern = &pattern[3..];
+            }
+            // a space matching a run of whitespace
+            b' ' if options.flexible_space => {
+                let run = string
+                    .iter()
+                    .take_while(|c| c.is_ascii_whitespace())
+                    .count();
+                return (1..=run)
+                    .rev()
+                    .any(|n| rest_matches(&pattern[1..], &string[n..], options, budget));
+            }
+            // shorthand classes such as `\d`
+            b'\\' if pattern.len() >= 2 && shorthand_matches(pattern[1], 0).is_some() => {
+                if is_boundary(string[0], options) {
+                    return false;
+                }
+                if shorthand_matches(pattern[1], string[0]) != Some(true) {
+                    return false;
+                }
+                pattern = &pattern[1..];
+                string = &string[1..];
+            }
             // everything else
             _ => {
                 // Ignore escaped characters
@@ -142,7 +904,7 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
                     }
                     string = &string[1..];
                 } else {
-                    if p.to_ascii_lowercase() != string[0].to_ascii_lowercase() {
+                    if !p.eq_ignore_ascii_case(&string[0]) {
                         return false;
                     }
                     string = &string[1..];
@@ -156,7 +918,8 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
             pattern = &pattern[1..];
         }
         if string.is_empty() {
-            while !pattern.is_empty() && pattern[0] == b'*' {
+            pattern = skip_unmatched_stars(pattern, options);
+            if options.optional_trailing_any && pattern == b"?" {
                 pattern = &pattern[1..];
             }
             break;
@@ -166,6 +929,106 @@ fn stringmatch_bytes(mut pattern: &[u8], mut string: &[u8], case: Case) -> bool
     pattern.is_empty() && string.is_empty()
 }
 
+/// Whether `c` separates the parts of a string that wildcards don't match across.
+fn is_boundary(c: u8, options: &MatchOptions) -> bool {
+    (options.path && c == b'/') || (options.dot_is_boundary && c == b'.')
+}
+
+/// Length of the element at the start of the pattern a `+` can repeat, or 0 if it can't be
+/// repeated.
+fn repeatable_len(pattern: &[u8]) -> usize {
+    match pattern[0] {
+        b'*' | b'{' => 0,
+        b'\\' => pattern.len().min(2),
+        b'[' => {
+            let mut len = 1;
+            if pattern.get(len) == Some(&b'^') {
+                len += 1;
+            }
+            // Mirrors how the bracket arm consumes the class.
+            while len < pattern.len() {
+                let rest = &pattern[len..];
+                if rest[0] == b'\\' && rest.len() >= 2 {
+                    len += 2;
+                } else if rest[0] == b']' {
+                    return len + 1;
+                } else if rest.len() >= 3 && rest[1] == b'-' {
+                    len += 3;
+                } else {
+                    len += 1;
+                }
+            }
+            pattern.len()
+        }
+        _ => 1,
+    }
+}
+
+/// Match the rest of a pattern, which may also be left with an empty string.
+fn rest_matches(rest: &[u8], string: &[u8], options: &MatchOptions, budget: usize) -> bool {
+    if string.is_empty() {
+        let rest = skip_unmatched_stars(rest, options);
+        return rest.is_empty() || (options.optional_trailing_any && rest == b"?");
+    }
+    stringmatch_bytes(rest, string, options, budget)
+}
+
+/// Whether `c` is in the shorthand class `\<class>`, or `None` if there is no such class.
+fn shorthand_matches(class: u8, c: u8) -> Option<bool> {
+    let matched = match class.to_ascii_lowercase() {
+        b'd' => c.is_ascii_digit(),
+        b'w' => c.is_ascii_alphanumeric() || c == b'_',
+        b's' => c.is_ascii_whitespace(),
+        _ => return None,
+    };
+    Some(matched != class.is_ascii_uppercase())
+}
+
+/// Skip the `*` wildcards at the start of the pattern that are left once the string is
+/// exhausted.
+///
+/// With `double_star_literal` a `**` is a literal that still needs to be matched.
+fn skip_unmatched_stars<'p>(pattern: &'p [u8], options: &MatchOptions) -> &'p [u8] {
+    if !options.double_star_literal {
+        return skip_trailing_stars(pattern);
+    }
+    match pattern {
+        [b'*', b'*', ..] => pattern,
+        [b'*', rest @ ..] => rest,
+        _ => pattern,
+    }
+}
+
+/// Skip all `*` at the start of the pattern.
+///
+/// Used once the string is exhausted, where the remaining stars match nothing, and to collapse
+/// consecutive stars, which match the same as a single one.
+fn skip_trailing_stars(pattern: &[u8]) -> &[u8] {
+    let stars = pattern.iter().take_while(|&&c| c == b'*').count();
+    &pattern[stars..]
+}
+
+/// Length of the balanced bracket span at the start of `string`, if there is one.
+fn balanced_span_len(string: &[u8]) -> Option<usize> {
+    let mut open = Vec::new();
+    for (i, &c) in string.iter().enumerate() {
+        match c {
+            b'(' => open.push(b')'),
+            b'[' => open.push(b']'),
+            b')' | b']' => match open.pop() {
+                Some(close) if close == c => {}
+                _ => return None,
+            },
+            _ if open.is_empty() => return None,
+            _ => {}
+        }
+        if open.is_empty() {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,12 +1088,40 @@ mod tests {
         assert!(!stringmatch("m[^n-p]enster", "moenster"));
     }
 
+    #[test]
+    fn reversed_range_in_composed_class() {
+        for pattern in &["m[0-9n-p]enster", "m[p-n0-9]enster", "m[_\\]p-n.]enster"] {
+            assert!(stringmatch(pattern, "moenster"), "{:?}", pattern);
+            assert!(Pattern::new(pattern).matches("moenster"), "{:?}", pattern);
+            assert!(!stringmatch(pattern, "mqenster"), "{:?}", pattern);
+            assert!(!Pattern::new(pattern).matches("mqenster"), "{:?}", pattern);
+        }
+        assert!(stringmatch("m[0-9p-n]enster", "m5enster"));
+        assert!(stringmatch("m[^9-0p-n]enster", "mqenster"));
+        assert!(!stringmatch("m[^9-0p-n]enster", "m5enster"));
+    }
+
     #[test]
     fn wrong_bracket() {
         assert!(stringmatch("m[n-p", "mo"));
         assert!(!stringmatch("m[n-pt", "mot"));
     }
 
+    #[test]
+    fn trailing_bracket() {
+        assert!(!stringmatch("a[", "ab"));
+        assert!(!Pattern::new("a[").matches("ab"));
+        assert!(!stringmatch_from("a[", "ab", 0));
+        assert!(!matches_with_wildcard_budget("*[", "ab", 10));
+        assert_eq!(
+            match_completeness("a[", "ab"),
+            Completeness::Complete(false)
+        );
+        assert_eq!(longest_matching_prefix_len("a[", "ab"), 0);
+        assert!(!stringmatch_version("[", "1"));
+        assert!(!stringmatch_lenprefixed(b"[", b"a"));
+    }
+
     #[test]
     fn escaped_in_bracket() {
         assert!(stringmatch("m[\\].;]o", "m]o"));
@@ -238,8 +1129,733 @@ mod tests {
         assert!(stringmatch("m[\\].;]o", "m.o"));
     }
 
+    #[test]
+    fn shorthand_classes() {
+        assert!(stringmatch("id-\\d\\d\\d", "id-042"));
+        assert!(!stringmatch("id-\\d\\d\\d", "id-04x"));
+        assert!(stringmatch("id-\\D", "id-x"));
+        assert!(!stringmatch("id-\\D", "id-4"));
+        assert!(stringmatch("\\w\\w\\w", "a_1"));
+        assert!(!stringmatch("\\w", "-"));
+        assert!(stringmatch("\\W", "-"));
+        assert!(!stringmatch("\\W", "a"));
+        assert!(stringmatch("a\\sb", "a b"));
+        assert!(stringmatch("a\\sb", "a\tb"));
+        assert!(!stringmatch("a\\sb", "a_b"));
+        assert!(stringmatch("a\\Sb", "a_b"));
+        assert!(!stringmatch("a\\Sb", "a b"));
+        assert!(stringmatch("\\\\d", "\\d"));
+        assert!(stringmatch("\\x", "x"));
+        assert!(!stringmatch_path("a\\Wb", "a/b"));
+    }
+
     #[test]
     fn empty_bracket() {
         assert!(!stringmatch("m[]", "m"));
     }
+
+    #[test]
+    fn case_insensitive() {
+        assert!(stringmatch_with_case(
+            "MOENSTER",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(stringmatch_with_case(
+            "m*NSTER",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(!stringmatch_with_case(
+            "MOENSTER",
+            "moenster",
+            Case::Sensitive
+        ));
+    }
+
+    #[test]
+    fn case_insensitive_escaped() {
+        assert!(stringmatch_with_case("\\A", "a", Case::Insensitive));
+        assert!(stringmatch_with_case("\\a", "A", Case::Insensitive));
+        assert!(!stringmatch_with_case("\\A", "a", Case::Sensitive));
+    }
+
+    #[test]
+    fn case_insensitive_bracket() {
+        assert!(stringmatch_with_case(
+            "m[OEI]enster",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(!stringmatch_with_case(
+            "m[BCD]enster",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(stringmatch_with_case(
+            "m[\\O]enster",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(stringmatch_with_case(
+            "m[N-P]enster",
+            "moenster",
+            Case::Insensitive
+        ));
+        assert!(!stringmatch_with_case(
+            "m[OEI]enster",
+            "moenster",
+            Case::Sensitive
+        ));
+    }
+
+    #[test]
+    fn any_case_insensitive() {
+        assert!(matches_any_ci(&["*.RS", "*.Toml"], "lib.rs"));
+        assert!(matches_any_ci(&["*.RS", "*.Toml"], "CARGO.TOML"));
+        assert!(matches_any_ci(&["M[O]ENSTER"], "moenster"));
+        assert!(!matches_any_ci(&["*.RS", "*.Toml"], "README.md"));
+        assert!(!matches_any_ci(&[], "moenster"));
+    }
+
+    #[test]
+    fn escape_group() {
+        assert_eq!(escape_for_group("a,b"), "a\\,b");
+        assert_eq!(escape_for_group("{x}(y|z)"), "\\{x\\}\\(y\\|z\\)");
+        assert_eq!(escape_for_group("*?[,-/]"), "*?[,-/]");
+        assert_eq!(escape_for_group("a\\,b\\"), "a\\,b\\");
+        assert_eq!(escape_for_group("[\\]|]|"), "[\\]|]\\|");
+
+        for pattern in &["a,b*", "f(x|y)?", "[,-/]{}", "[^a,]*,*", "x\\,y"] {
+            let escaped = escape_for_group(pattern);
+            for string in &["a,bc", "f(x|y)!", ".{}", "b,,", "x,y", ""] {
+                assert_eq!(
+                    stringmatch(&escaped, string),
+                    stringmatch(pattern, string),
+                    "{:?} against {:?}",
+                    escaped,
+                    string
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lenient() {
+        assert!(matches_lenient("m[n-p]enster", "moenster"));
+        assert!(matches_lenient("m*\\*", "mønster*"));
+        assert!(!matches_lenient("m[n-p]enster", "m[n-p]enster"));
+
+        assert!(stringmatch("m[n-p", "mo"));
+        assert!(!matches_lenient("m[n-p", "mo"));
+        assert!(matches_lenient("m[n-p", "m[n-p"));
+        assert!(matches_lenient("moenster\\", "moenster\\"));
+        assert!(!matches_lenient("*\\", "moenster\\"));
+    }
+
+    #[test]
+    fn line_anchored() {
+        let text = "start of text\n  indented error\nerror: first\nerror: second\n";
+        assert_eq!(find_line_anchored("error*", text), Some(31..43));
+        assert_eq!(find_line_anchored("error: s*", text), Some(44..57));
+        assert_eq!(find_line_anchored("*error", text), Some(14..30));
+        assert_eq!(find_line_anchored("st?rt", text), Some(0..5));
+        assert_eq!(find_line_anchored("text*error", text), None);
+        assert_eq!(find_line_anchored("indented*", text), None);
+        assert_eq!(find_line_anchored("ø*", "a\nøl"), Some(2..5));
+        assert_eq!(find_line_anchored("*", ""), None);
+
+        // The empty pattern matches the empty start of every line.
+        assert_eq!(find_line_anchored("", ""), Some(0..0));
+        assert_eq!(find_line_anchored("", "a\n\nb"), Some(0..0));
+        assert_eq!(find_line_anchored("", "\nb"), Some(0..0));
+    }
+
+    #[test]
+    fn trim_matching() {
+        assert_eq!(
+            super::trim_matching("log-*", "log-abc.txt"),
+            Some("abc.txt")
+        );
+        assert_eq!(
+            super::trim_matching("*.txt", "log-abc.txt"),
+            Some("log-abc")
+        );
+        assert_eq!(
+            super::trim_matching("log-*.txt", "log-abc.txt"),
+            Some("abc")
+        );
+        assert_eq!(super::trim_matching("log-*.txt", "log-.txt"), Some(""));
+        assert_eq!(super::trim_matching("l?g-*", "lag-abc"), Some("ag-abc"));
+        assert_eq!(super::trim_matching("[lm]og-?", "mog-ä"), None);
+        assert_eq!(super::trim_matching("[lm]og-*", "mog-ä"), Some("mog-ä"));
+        assert_eq!(super::trim_matching("*", "abc"), Some("abc"));
+        assert_eq!(super::trim_matching("abc", "abc"), Some(""));
+
+        assert_eq!(super::trim_matching("log-*.txt", "log.txt"), None);
+    }
+
+    #[test]
+    fn all_captures() {
+        assert_eq!(
+            super::all_captures("*-*-*", "a-b-c-d"),
+            vec![
+                vec!["a", "b", "c-d"],
+                vec!["a", "b-c", "d"],
+                vec!["a-b", "c", "d"]
+            ]
+        );
+        assert_eq!(
+            super::all_captures("*?*", "ab"),
+            vec![vec!["", "b"], vec!["a", ""]]
+        );
+        assert_eq!(super::all_captures("a**b", "axb"), vec![vec!["x"]]);
+        assert_eq!(
+            super::all_captures("a?b", "axb"),
+            vec![Vec::<String>::new()]
+        );
+        assert!(super::all_captures("*x*", "aaa").is_empty());
+        assert!(super::all_captures("*", "").is_empty());
+
+        // Four stars over 100 bytes can split them in far more ways than are returned.
+        let string = "a".repeat(100);
+        assert_eq!(super::all_captures("****", &string).len(), 1);
+        assert_eq!(
+            super::all_captures("*a*a*a*", &string).len(),
+            ALL_CAPTURES_LIMIT
+        );
+    }
+
+    #[test]
+    fn tokenize() {
+        use TokenKind::*;
+
+        assert_eq!(
+            super::tokenize("k[a-z]y=*;*", "key=a;b=c;"),
+            Some(vec![
+                (Literal, 0..1),
+                (Class, 1..2),
+                (Literal, 2..4),
+                (AnyString, 4..5),
+                (Literal, 5..6),
+                (AnyString, 6..10),
+            ])
+        );
+        assert_eq!(
+            super::tokenize("??*\\*", "ab*"),
+            Some(vec![
+                (AnyChar, 0..1),
+                (AnyChar, 1..2),
+                (AnyString, 2..2),
+                (Literal, 2..3),
+            ])
+        );
+        assert_eq!(super::tokenize("", ""), Some(vec![]));
+        assert_eq!(super::tokenize("k*", "x"), None);
+    }
+
+    #[test]
+    fn wildcard_consumption() {
+        assert_eq!(super::wildcard_consumption("monster", "monster"), Some(0));
+        assert_eq!(super::wildcard_consumption("m[aeiou]n\\*", "mon*"), Some(0));
+        assert_eq!(super::wildcard_consumption("mon*", "mon"), Some(0));
+        assert_eq!(super::wildcard_consumption("*", "monster"), Some(7));
+        assert_eq!(super::wildcard_consumption("m*n*r", "monster"), Some(4));
+        assert_eq!(super::wildcard_consumption("*o*s**", "monster"), Some(5));
+
+        assert_eq!(super::wildcard_consumption("m*x", "monster"), None);
+        assert_eq!(super::wildcard_consumption("*", ""), None);
+    }
+
+    #[test]
+    fn completeness() {
+        use Completeness::*;
+
+        assert_eq!(match_completeness("ab*cd", "abxcd"), Complete(true));
+        assert_eq!(match_completeness("ab*cd", ""), Incomplete);
+        assert_eq!(match_completeness("ab*cd", "a"), Incomplete);
+        assert_eq!(match_completeness("ab*cd", "abxyc"), Incomplete);
+        assert_eq!(match_completeness("ab*cd", "abxcdx"), Incomplete);
+        assert_eq!(match_completeness("a[bc]d", "ac"), Incomplete);
+        assert_eq!(match_completeness("*", ""), Incomplete);
+        assert_eq!(match_completeness("ä?", "ä"), Incomplete);
+
+        assert_eq!(match_completeness("ab*cd", "x"), Complete(false));
+        assert_eq!(match_completeness("a[bc]d", "ax"), Complete(false));
+        assert_eq!(match_completeness("abc", "abcd"), Complete(false));
+        // Nothing can follow, as `[]` matches nothing.
+        assert_eq!(match_completeness("a[]", "a"), Complete(false));
+    }
+
+    #[test]
+    fn prefix_while_typing() {
+        let progress: Vec<_> = [
+            "",
+            "m",
+            "mo",
+            "moX",
+            "moXn",
+            "moXnst",
+            "moXnster",
+            "moXnsterz",
+        ]
+        .iter()
+        .map(|partial| longest_matching_prefix_len("mo*nster", partial))
+        .collect();
+        assert_eq!(progress, vec![0, 1, 3, 3, 4, 6, 8, 3]);
+
+        assert_eq!(longest_matching_prefix_len("*.rs", ""), 1);
+        assert_eq!(longest_matching_prefix_len("[a-c]\\?x", "b"), 5);
+        assert_eq!(longest_matching_prefix_len("[a-c]\\?x", "b?"), 7);
+        assert_eq!(longest_matching_prefix_len("[a-c]\\?x", "b?x"), 8);
+        assert_eq!(longest_matching_prefix_len("abc", "x"), 0);
+        assert_eq!(longest_matching_prefix_len("abc", "abcd"), 0);
+        assert_eq!(longest_matching_prefix_len("", "a"), 0);
+    }
+
+    #[test]
+    fn search_query() {
+        assert!(search("foo bar", "foobar"));
+        assert!(search("foo bar", "the BAR and the Foo"));
+        assert!(search("  foo\tbar ", "bar foo"));
+        assert!(!search("foo bar", "foo only"));
+
+        assert!(search("\"foo bar\" baz", "baz, foo bar"));
+        assert!(!search("\"foo bar\"", "foo, bar"));
+        assert!(search("\"a b", "xa b"));
+        assert!(search("\"\" x", "x"));
+
+        assert!(search("*.rs", "lib*.rs"));
+        assert!(!search("*.rs", "lib.rs"));
+        assert!(search("[a] ø", "[A] Ø ø"));
+        assert!(search("", "anything"));
+
+        assert_eq!(search_terms("a \"b c\"d  \"e"), vec!["a", "b c", "d", "e"]);
+    }
+
+    #[test]
+    fn all_segments() {
+        assert!(matches_all_segments("[a-z]*", "ab,cd,ef", ','));
+        assert!(matches_all_segments("[a-z]*", "ab", ','));
+        assert!(matches_all_segments("*=?", "a=1;bb=2", ';'));
+        assert!(matches_all_segments("ø*", "øl ømt", ' '));
+
+        assert!(!matches_all_segments("[a-z]*", "ab,12,ef", ','));
+        assert!(!matches_all_segments("[a-z]*", "12,cd,ef", ','));
+        assert!(!matches_all_segments("[a-z]*", "ab,cd,", ','));
+        assert!(!matches_all_segments("[a-z]*", "ab,,cd", ','));
+    }
+
+    #[test]
+    fn validate_samples() {
+        let samples = ["moenster", "monster", "mønster", "master", "", "moensters"];
+        assert_eq!(validate_against("m?nster", &samples), vec![0, 2, 3, 4, 5]);
+        assert_eq!(validate_against("m*nster", &samples), vec![3, 4, 5]);
+        assert_eq!(validate_against("m*", &samples), vec![4]);
+        assert!(validate_against("m*", &[]).is_empty());
+    }
+
+    #[test]
+    fn tolerance() {
+        assert!(matches_with_tolerance("moenster", "moenster", 0));
+        assert!(!matches_with_tolerance("moenster", "mounster", 0));
+        assert!(matches_with_tolerance("moenster", "mounster", 1));
+        assert!(!matches_with_tolerance("moenster", "mounstor", 1));
+        assert!(matches_with_tolerance("moenster", "mounstor", 2));
+        assert!(matches_with_tolerance("m[aeiou]?nster", "mxxnster", 1));
+        assert!(!matches_with_tolerance("moenster", "moenste", 2));
+        assert!(!matches_with_tolerance("moenster", "moensterr", 2));
+        assert!(matches_with_tolerance("m*nster", "moendter", 1));
+    }
+
+    #[test]
+    fn between() {
+        assert_eq!(match_between("user=", ";", "user=42;"), Some("42"));
+        assert_eq!(match_between("user=", ";", "user=;"), Some(""));
+        assert_eq!(
+            match_between("*=", ".*", "name=moenster.rs"),
+            Some("moenster")
+        );
+        assert_eq!(match_between("", "", "moenster"), Some("moenster"));
+        assert_eq!(match_between("m??", "r", "mønster"), Some("nste"));
+        assert_eq!(match_between("user=", ";", "name=42;"), None);
+        assert_eq!(match_between("user=", ";", "user=42"), None);
+    }
+
+    #[test]
+    fn between_overlapping() {
+        assert_eq!(match_between("ab", "bc", "abc"), None);
+        assert_eq!(match_between("us*=", "=4*", "us=42"), None);
+    }
+
+    #[test]
+    fn unsatisfiable_token() {
+        assert_eq!(
+            first_unsatisfiable_token("m[aeiou]nster", "moenster"),
+            Some(2)
+        );
+        assert_eq!(
+            first_unsatisfiable_token("m[aeiou]nster", "mxnster"),
+            Some(1)
+        );
+        assert_eq!(
+            first_unsatisfiable_token("m[aeiou]nster", "monsteX"),
+            Some(6)
+        );
+        assert_eq!(first_unsatisfiable_token("m[aeiou]nster", "monst"), Some(5));
+        assert_eq!(
+            first_unsatisfiable_token("m[aeiou]nster", "monsters"),
+            Some(7)
+        );
+        assert_eq!(first_unsatisfiable_token("m[aeiou]nster", ""), Some(0));
+        assert_eq!(first_unsatisfiable_token("*[0-9]x", "a1b2c"), Some(2));
+        assert_eq!(first_unsatisfiable_token("m**n?st", "moenster"), Some(4));
+        assert_eq!(first_unsatisfiable_token("m*r", "moenster"), None);
+    }
+
+    #[test]
+    fn shadowed_patterns() {
+        assert_eq!(
+            find_shadowed(&["*.rs", "README.md", "src/*.rs", "*.md", "README.md"]),
+            vec![2, 4]
+        );
+        assert_eq!(
+            find_shadowed(&["src/*.rs", "*.rs", "*.md"]),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn path_mode() {
+        assert!(stringmatch_path("src/*.rs", "src/lib.rs"));
+        assert!(!stringmatch_path("src/*.rs", "src/bin/main.rs"));
+        assert!(!stringmatch_path("src*", "src/lib.rs"));
+        assert!(!stringmatch_path("src?lib.rs", "src/lib.rs"));
+        assert!(!stringmatch_path("src[/]lib.rs", "src/lib.rs"));
+        assert!(stringmatch_path("src/**", "src/bin/main.rs"));
+        assert!(stringmatch_path("**/*.rs", "src/bin/main.rs"));
+        assert!(stringmatch_path("**/*.rs", "main.rs"));
+        assert!(stringmatch_path("src/**/*.rs", "src/lib.rs"));
+        assert!(stringmatch_path("src/**/main.rs", "src/bin/main.rs"));
+        assert!(!stringmatch_path("src/**/*.rs", "tests/lib.rs"));
+    }
+
+    #[test]
+    fn path_mode_directories() {
+        assert!(stringmatch_path("logs/", "logs/"));
+        assert!(!stringmatch_path("logs/", "logs"));
+        assert!(!stringmatch_path("logs/", "logs/app.log"));
+        assert!(stringmatch_path("*/", "logs/"));
+        assert!(!stringmatch_path("*/", "logs/2024/"));
+
+        assert!(stringmatch_path("logs/**", "logs/app.log"));
+        assert!(stringmatch_path("logs/**", "logs/2024/app.log"));
+        assert!(stringmatch_path("logs/**", "logs/"));
+        assert!(stringmatch_path("logs/**/", "logs/2024/"));
+        assert!(!stringmatch_path("logs/**/", "logs/2024/app.log"));
+        assert!(stringmatch_path("**/build/", "target/debug/build/"));
+    }
+
+    #[test]
+    fn from_offset() {
+        assert!(stringmatch_from("moenster", "moenster", 0));
+        assert!(stringmatch_from("enst*", "moenster", 2));
+        assert!(!stringmatch_from("moenster", "moenster", 2));
+        assert!(stringmatch_from("r", "moenster", 7));
+        assert!(stringmatch_from("", "moenster", 8));
+        assert!(!stringmatch_from("*", "moenster", 8));
+        assert!(stringmatch_from("", "moenster", 100));
+        assert!(!stringmatch_from("?", "moenster", 100));
+    }
+
+    #[test]
+    fn optional_trailing_any() {
+        let options = MatchOptions {
+            optional_trailing_any: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("abc?", "abc", &options));
+        assert!(stringmatch_with_options("abc?", "abcd", &options));
+        assert!(!stringmatch_with_options("abc?", "abcde", &options));
+        assert!(stringmatch_with_options("abc*?", "abc", &options));
+        assert!(!stringmatch_with_options("ab?c", "abc", &options));
+        assert!(!stringmatch_with_options("abc\\?", "abc", &options));
+        assert!(!stringmatch("abc?", "abc"));
+    }
+
+    #[test]
+    fn collapse_whitespace() {
+        let options = MatchOptions {
+            collapse_whitespace: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("a b", "a    b", &options));
+        assert!(stringmatch_with_options("a b", "a \t\n b", &options));
+        assert!(stringmatch_with_options(" a", "   a", &options));
+        assert!(stringmatch_with_options("a ", "a\t\t", &options));
+        assert!(stringmatch_with_options("a?b", "a  b", &options));
+        assert!(!stringmatch_with_options("ab", "a  b", &options));
+        assert!(!stringmatch_with_options("a  b", "a  b", &options));
+        assert!(!stringmatch_with_options(
+            "a b",
+            "a  b",
+            &MatchOptions::default()
+        ));
+    }
+
+    #[test]
+    fn plus_quantifier() {
+        let options = MatchOptions {
+            plus_quantifier: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("a+", "a", &options));
+        assert!(stringmatch_with_options("a+", "aaa", &options));
+        assert!(!stringmatch_with_options("a+", "aab", &options));
+        assert!(!stringmatch_with_options("a+", "b", &options));
+        assert!(stringmatch_with_options("[0-9]+", "2024", &options));
+        assert!(stringmatch_with_options(
+            "v[0-9]+.[0-9]+",
+            "v10.2",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "v[0-9]+.[0-9]+",
+            "v10.",
+            &options
+        ));
+        assert!(stringmatch_with_options("a+ab", "aaab", &options));
+        assert!(stringmatch_with_options("x?+y*", "xaby", &options));
+        assert!(stringmatch_with_options("\\d+\\s+*", "12 \tkg", &options));
+        assert!(stringmatch_with_options("[^,]+,", "ab,", &options));
+        assert!(stringmatch_with_options("a\\+", "a+", &options));
+        assert!(!stringmatch_with_options("a\\+", "aa", &options));
+        assert!(stringmatch_with_options("*+", "a+", &options));
+        assert!(!stringmatch_with_options("*+", "aa", &options));
+
+        assert!(stringmatch("a+", "a+"));
+        assert!(!stringmatch("a+", "aa"));
+        assert!(!stringmatch("[0-9]+", "12"));
+    }
+
+    #[test]
+    fn flexible_space() {
+        let options = MatchOptions {
+            flexible_space: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("a b", "a b", &options));
+        assert!(stringmatch_with_options("a b", "a   b", &options));
+        assert!(stringmatch_with_options("a b", "a\tb", &options));
+        assert!(stringmatch_with_options("a b", "a \t\n b", &options));
+        assert!(stringmatch_with_options("a  b", "a  b", &options));
+        assert!(stringmatch_with_options("a *b", "a   b", &options));
+        assert!(stringmatch_with_options("a ", "a  ", &options));
+        assert!(!stringmatch_with_options("a b", "ab", &options));
+        assert!(!stringmatch_with_options("a  b", "a b", &options));
+        assert!(!stringmatch_with_options("a\\ b", "a  b", &options));
+        assert!(stringmatch_with_options("a\\ b", "a b", &options));
+        assert!(!stringmatch("a b", "a  b"));
+    }
+
+    #[test]
+    fn dot_is_boundary() {
+        let options = MatchOptions {
+            dot_is_boundary: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options(
+            "*.example.com",
+            "www.example.com",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "*.example.com",
+            "a.b.example.com",
+            &options
+        ));
+        assert!(stringmatch_with_options(
+            "*.*.example.com",
+            "a.b.example.com",
+            &options
+        ));
+        assert!(stringmatch_with_options(
+            "**.example.com",
+            "a.b.example.com",
+            &options
+        ));
+        assert!(stringmatch_with_options(
+            "example.*",
+            "example.com",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "example.*",
+            "example.co.uk",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "example?com",
+            "example.com",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "example[.]com",
+            "example.com",
+            &options
+        ));
+        assert!(stringmatch_with_options("a/*", "a/b/c", &options));
+        assert!(stringmatch("*.example.com", "a.b.example.com"));
+
+        let both = MatchOptions {
+            path: true,
+            ..options
+        };
+        assert!(!stringmatch_with_options("*", "a/b", &both));
+        assert!(!stringmatch_with_options("*", "a.b", &both));
+        assert!(stringmatch_with_options(
+            "**/*.rs",
+            "src/bin/main.rs",
+            &both
+        ));
+    }
+
+    #[test]
+    fn double_star_literal() {
+        let options = MatchOptions {
+            double_star_literal: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("a**b", "a*b", &options));
+        assert!(!stringmatch_with_options("a**b", "axb", &options));
+        assert!(!stringmatch_with_options("a**b", "ab", &options));
+        assert!(stringmatch_with_options("a**", "a*", &options));
+        assert!(!stringmatch_with_options("a**", "a", &options));
+        assert!(stringmatch_with_options("a***", "a*xyz", &options));
+        assert!(stringmatch_with_options("a***", "a*", &options));
+        assert!(stringmatch_with_options("a*b", "axyzb", &options));
+        assert!(stringmatch_with_options("****", "**", &options));
+        assert!(!stringmatch_with_options("****", "*", &options));
+        assert!(stringmatch("a**b", "axyzb"));
+
+        let path = MatchOptions {
+            path: true,
+            ..options
+        };
+        assert!(stringmatch_with_options("src/**", "src/*", &path));
+        assert!(!stringmatch_with_options("src/**", "src/a/b", &path));
+    }
+
+    #[test]
+    fn any_allows() {
+        let options = MatchOptions {
+            any_allows: Some((b'0'..=b'9').collect()),
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("v?.?", "v1.2", &options));
+        assert!(!stringmatch_with_options("v?.?", "vx.2", &options));
+        assert!(!stringmatch_with_options("v?.?", "v1.?", &options));
+        assert!(stringmatch_with_options("v*.?", "vx.2", &options));
+        assert!(stringmatch_with_options("v[a-z]", "vx", &options));
+        assert!(stringmatch_with_options("v\\?", "v?", &options));
+
+        let path = MatchOptions {
+            path: true,
+            any_allows: Some(b"/a".to_vec()),
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options("?", "a", &path));
+        assert!(!stringmatch_with_options("a?b", "a/b", &path));
+    }
+
+    #[test]
+    fn range_exclusive_end() {
+        let options = MatchOptions {
+            range_exclusive_end: true,
+            ..MatchOptions::default()
+        };
+        for (pattern, string, inclusive, exclusive) in [
+            ("[a-d]", "a", true, true),
+            ("[a-d]", "c", true, true),
+            ("[a-d]", "d", true, false),
+            ("[d-a]", "d", true, false),
+            ("[d-a]", "a", true, true),
+            ("[a-a]", "a", true, false),
+            ("[^a-d]", "d", false, true),
+            ("[a-dx]", "x", true, true),
+            ("[0-9]*", "9.5", true, false),
+        ] {
+            assert_eq!(stringmatch(pattern, string), inclusive, "{}", pattern);
+            assert_eq!(
+                stringmatch_with_options(pattern, string, &options),
+                exclusive,
+                "{}",
+                pattern
+            );
+        }
+
+        let insensitive = MatchOptions {
+            case: Case::Insensitive,
+            ..options
+        };
+        assert!(stringmatch_with_options("[A-D]", "c", &insensitive));
+        assert!(!stringmatch_with_options("[A-D]", "d", &insensitive));
+    }
+
+    #[test]
+    fn balanced_groups() {
+        let options = MatchOptions {
+            balanced_groups: true,
+            ..MatchOptions::default()
+        };
+        assert!(stringmatch_with_options(
+            "call{{}}end",
+            "call(a(b)c)end",
+            &options
+        ));
+        assert!(stringmatch_with_options("call{{}}", "call[a(b)]", &options));
+        assert!(stringmatch_with_options("{{}}*", "()rest", &options));
+        assert!(!stringmatch_with_options(
+            "call{{}}end",
+            "call(a(b)end",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "call{{}}end",
+            "call(a]end",
+            &options
+        ));
+        assert!(!stringmatch_with_options(
+            "call{{}}end",
+            "callend",
+            &options
+        ));
+        assert!(!stringmatch_with_options("call{{}}", "call(a)b", &options));
+        assert!(stringmatch_with_options("call\\{{}}", "call{{}}", &options));
+        assert!(stringmatch("call{{}}", "call{{}}"));
+    }
+
+    #[test]
+    fn trailing_stars() {
+        assert_eq!(skip_trailing_stars(b""), b"");
+        assert_eq!(skip_trailing_stars(b"*"), b"");
+        assert_eq!(skip_trailing_stars(b"***"), b"");
+        assert_eq!(skip_trailing_stars(b"**a*"), b"a*");
+        assert_eq!(skip_trailing_stars(b"a*"), b"a*");
+        assert_eq!(skip_trailing_stars(b"\\*"), b"\\*");
+        assert!(stringmatch("moenster***", "moenster"));
+        assert!(!stringmatch("moenster*?*", "moenster"));
+    }
+
+    #[test]
+    fn wildcard_budget() {
+        assert!(matches_with_wildcard_budget("moenster", "moenster", 0));
+        assert!(matches_with_wildcard_budget("m*r", "moenster", 6));
+        assert!(!matches_with_wildcard_budget("m*r", "moenster", 5));
+        assert!(matches_with_wildcard_budget("*-*", "abc-def", 6));
+        assert!(!matches_with_wildcard_budget("*-*", "abc-def", 5));
+        assert!(matches_with_wildcard_budget("*", "moenster", 8));
+        assert!(!matches_with_wildcard_budget("*", "moenster", 7));
+        assert!(matches_with_wildcard_budget("moenster*", "moenster", 0));
+    }
 }
@@ -0,0 +1,102 @@
+//! Matching against the elements of a list written as a string.
+
+use crate::stringmatch;
+
+/// Match the elements of a list against the specified pattern.
+///
+/// The string is a list like `[a, b, c]`: comma-separated elements inside square brackets.
+/// Returns true if the pattern matches any of the elements.
+///
+/// Whitespace around elements is ignored. Elements can be quoted with `"` to contain commas,
+/// brackets or surrounding whitespace; the quotes are not part of the element and `\` escapes
+/// the next character inside them. Strings that are not enclosed in brackets don't match.
+///
+/// ```
+/// # use moenster::matches_list_element;
+/// assert!(matches_list_element("b*", "[apple, banana, cherry]"));
+/// assert!(matches_list_element("a,*", r#"["a,b", c]"#));
+/// ```
+pub fn matches_list_element(pattern: &str, string: &str) -> bool {
+    let inner = match string
+        .trim()
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+
+    split_elements(inner)
+        .iter()
+        .any(|element| stringmatch(pattern, element))
+}
+
+fn split_elements(list: &str) -> Vec<String> {
+    let mut elements = Vec::new();
+    if list.trim().is_empty() {
+        return elements;
+    }
+
+    let mut element = String::new();
+    // Whitespace that is only kept if more of the element follows.
+    let mut pending = String::new();
+    let mut chars = list.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ',' => {
+                pending.clear();
+                elements.push(std::mem::take(&mut element));
+                continue;
+            }
+            c if c.is_whitespace() => {
+                if !element.is_empty() {
+                    pending.push(c);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        element.push_str(&pending);
+        pending.clear();
+        match c {
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => element.extend(chars.next()),
+                        c => element.push(c),
+                    }
+                }
+            }
+            c => element.push(c),
+        }
+    }
+    elements.push(element);
+
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_list() {
+        assert!(matches_list_element("b", "[a,b,c]"));
+        assert!(matches_list_element("b*", "[apple, banana, cherry]"));
+        assert!(!matches_list_element("d", "[a,b,c]"));
+        assert!(!matches_list_element("b", "a,b,c"));
+        assert!(!matches_list_element("*", "[]"));
+        assert!(matches_list_element("a b", "[ a b , c ]"));
+    }
+
+    #[test]
+    fn quoted_elements() {
+        assert!(matches_list_element("a,b", r#"["a,b", c]"#));
+        assert!(!matches_list_element("a", r#"["a,b", c]"#));
+        assert!(matches_list_element("c", r#"["a,b", c]"#));
+        assert!(matches_list_element(" x ", r#"[" x "]"#));
+        assert!(matches_list_element("say \"hi\"", r#"["say \"hi\""]"#));
+    }
+}
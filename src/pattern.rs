@@ -0,0 +1,2462 @@
+//! Compiled patterns.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::ops::{Range, RangeInclusive};
+use std::str;
+use std::time::{Duration, Instant};
+
+use crate::{Captures, Case, DepthExceeded, FromBytesError, Matcher, NotAscii, PatternError};
+
+/// A compiled pattern.
+///
+/// Parses the pattern once, so it can be matched against many strings without re-parsing it
+/// every time. Matches exactly like [`stringmatch`](crate::stringmatch).
+///
+/// A compiled pattern owns all its data and doesn't borrow the source string, so it can be
+/// stored for as long as needed.
+///
+/// ```
+/// # use moenster::Pattern;
+/// let pattern = Pattern::new("m*nster");
+/// assert!(pattern.matches("mønster"));
+/// assert!(pattern.matches("monster"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+    /// The literal bytes the pattern starts with, checked in one go before the remaining tokens.
+    prefix: Vec<u8>,
+    /// Set for patterns created with `new_ci`: all literals are already lowercased.
+    lowered: bool,
+    /// For each token, the skip tables of the literals following it if it is a `*` followed
+    /// by enough of them.
+    skips: Vec<Option<Box<StarSkip>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A single byte.
+    Literal(u8),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyString,
+    /// `[...]`
+    Class {
+        negated: bool,
+        members: Vec<ClassMember>,
+        /// The bytes the members match, precomputed from `members`.
+        set: ClassSet,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClassMember {
+    Byte(u8),
+    /// An inclusive range, with `start <= end`.
+    Range(u8, u8),
+}
+
+/// The bytes matched by the members of a class, one bit per byte value.
+///
+/// Lets matching test a byte with a single lookup instead of scanning all members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ClassSet {
+    /// Bytes matched as they are.
+    exact: [u64; 4],
+    /// Lowercase bytes matched when the members are lowercased too.
+    folded: [u64; 4],
+}
+
+impl ClassSet {
+    fn new(members: &[ClassMember]) -> ClassSet {
+        let mut set = ClassSet {
+            exact: [0; 4],
+            folded: [0; 4],
+        };
+        for member in members {
+            let (start, end) = match *member {
+                ClassMember::Byte(b) => (b, b),
+                ClassMember::Range(start, end) => (start, end),
+            };
+            for c in start..=end {
+                set.exact[usize::from(c / 64)] |= 1 << (c % 64);
+            }
+            // Lowercasing the bounds of a range can make it empty, like `Z-a`.
+            for c in start.to_ascii_lowercase()..=end.to_ascii_lowercase() {
+                set.folded[usize::from(c / 64)] |= 1 << (c % 64);
+            }
+        }
+        set
+    }
+
+    /// The only byte matched as it is, if there is exactly one.
+    fn single(&self) -> Option<u8> {
+        let mut bytes = (0..=255).filter(|&c| self.contains(c, Fold::None));
+        match (bytes.next(), bytes.next()) {
+            (Some(b), None) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn contains(&self, c: u8, fold: Fold) -> bool {
+        let (bits, c) = match fold {
+            Fold::None => (&self.exact, c),
+            Fold::Both => (&self.folded, c.to_ascii_lowercase()),
+            Fold::String => (&self.exact, c.to_ascii_lowercase()),
+            Fold::Pattern => (&self.folded, c),
+        };
+        bits[usize::from(c / 64)] & (1 << (c % 64)) != 0
+    }
+}
+
+impl Pattern {
+    /// Compile a pattern.
+    ///
+    /// See the top-level documentation for allowed wildcards.
+    ///
+    /// Malformed patterns are accepted and match the same as with
+    /// [`stringmatch`](crate::stringmatch). Use [`Pattern::try_new`] to reject them instead.
+    pub fn new(pattern: &str) -> Pattern {
+        Pattern::from_bytes_lenient(pattern.as_bytes())
+    }
+
+    /// Compile a pattern, rejecting malformed ones.
+    ///
+    /// ```
+    /// # use moenster::{Pattern, PatternError};
+    /// assert!(Pattern::try_new("m[a-z]nster").is_ok());
+    /// assert_eq!(
+    ///     Pattern::try_new("m[a-z").unwrap_err(),
+    ///     PatternError::UnclosedBracket { position: 1 }
+    /// );
+    /// ```
+    pub fn try_new(pattern: &str) -> Result<Pattern, PatternError> {
+        let (tokens, errors) = parse(pattern.as_bytes());
+        match errors
+            .into_iter()
+            .find(|e| !matches!(e, PatternError::EmptyBracket { .. }))
+        {
+            Some(error) => Err(error),
+            None => Ok(Pattern::from_tokens(tokens, false)),
+        }
+    }
+
+    /// Compile a pattern, rejecting malformed ones and likely mistakes.
+    ///
+    /// Works like [`Pattern::try_new`], but also rejects an empty bracket `[]`, which can never
+    /// match anything.
+    ///
+    /// ```
+    /// # use moenster::{Pattern, PatternError};
+    /// assert_eq!(
+    ///     Pattern::try_new_strict("a[]b").unwrap_err(),
+    ///     PatternError::EmptyBracket { position: 1 }
+    /// );
+    /// ```
+    pub fn try_new_strict(pattern: &str) -> Result<Pattern, PatternError> {
+        let (tokens, errors) = parse(pattern.as_bytes());
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(Pattern::from_tokens(tokens, false)),
+        }
+    }
+
+    /// Compile a pattern from raw bytes.
+    ///
+    /// The bytes don't need to be valid UTF-8 and malformed patterns are accepted, like with
+    /// [`Pattern::new`]. Use [`Pattern::try_from`] to validate both.
+    pub fn from_bytes_lenient(pattern: &[u8]) -> Pattern {
+        Pattern::from_tokens(parse(pattern).0, false)
+    }
+
+    /// Compile a pattern for case-insensitive matching.
+    ///
+    /// Literals are lowercased once during compilation, so matching only needs to fold the
+    /// string. Patterns compiled this way always match case-insensitively.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new_ci("M*NSTER");
+    /// assert!(pattern.matches("mønster"));
+    /// ```
+    pub fn new_ci(pattern: &str) -> Pattern {
+        let mut tokens = parse(pattern.as_bytes()).0;
+        for token in &mut tokens {
+            match token {
+                Token::Literal(b) => *b = b.to_ascii_lowercase(),
+                Token::Class { members, set, .. } => {
+                    for member in members.iter_mut() {
+                        match member {
+                            ClassMember::Byte(b) => *b = b.to_ascii_lowercase(),
+                            ClassMember::Range(start, end) => {
+                                *start = start.to_ascii_lowercase();
+                                *end = end.to_ascii_lowercase();
+                            }
+                        }
+                    }
+                    *set = ClassSet::new(members);
+                }
+                Token::AnyChar | Token::AnyString => {}
+            }
+        }
+
+        Pattern::from_tokens(tokens, true)
+    }
+
+    fn from_tokens(mut tokens: Vec<Token>, lowered: bool) -> Pattern {
+        tokens.shrink_to_fit();
+        let prefix = tokens
+            .iter()
+            .map_while(|token| match token {
+                Token::Literal(b) => Some(*b),
+                _ => None,
+            })
+            .collect();
+
+        let skips = (0..tokens.len())
+            .map(|i| {
+                if tokens[i] != Token::AnyString {
+                    return None;
+                }
+                let run = literal_run(&tokens[i + 1..]);
+                if run.len() < SKIP_MIN_RUN {
+                    return None;
+                }
+                let lowered = run.to_ascii_lowercase();
+                Some(Box::new(StarSkip {
+                    exact: SkipTable::new(run),
+                    lowered: SkipTable::new(lowered),
+                }))
+            })
+            .collect();
+
+        Pattern {
+            tokens,
+            prefix,
+            lowered,
+            skips,
+        }
+    }
+
+    /// How a `*` can search for the literals following it when matching with `fold`.
+    fn search(&self, fold: Fold) -> Search<'_> {
+        match fold {
+            Fold::None => Search::Exact(&self.skips),
+            Fold::Pattern => Search::Lowercase(&self.skips),
+            Fold::Both | Fold::String => Search::Scan,
+        }
+    }
+
+    /// Copy this pattern for long-term storage.
+    ///
+    /// Patterns never borrow their source string, so this is the same as `clone` and the copy
+    /// is always `'static`.
+    pub fn clone_static(&self) -> Pattern {
+        self.clone()
+    }
+
+    /// Match a string against this pattern.
+    ///
+    /// Returns true if the string matches against the pattern from start to finish.
+    pub fn matches(&self, string: &str) -> bool {
+        self.matches_bytes(string.as_bytes())
+    }
+
+    /// Render the parsed structure of this pattern as an indented tree, one element per line.
+    ///
+    /// Consecutive literals are shown together; bracketed patterns list their members.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert_eq!(
+    ///     Pattern::new("ab*[^x-z_]").debug_tree(),
+    ///     "Pattern\n  Literal \"ab\"\n  AnyString\n  Class negated\n    Range 'x'-'z'\n    Byte '_'\n"
+    /// );
+    /// ```
+    pub fn debug_tree(&self) -> String {
+        let mut tree = String::from("Pattern\n");
+        let mut literals = Vec::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token {
+                Token::Literal(b) => {
+                    literals.push(*b);
+                    if let Some(Token::Literal(_)) = self.tokens.get(i + 1) {
+                        continue;
+                    }
+                    let text = match str::from_utf8(&literals) {
+                        Ok(text) => format!("{:?}", text),
+                        Err(_) => format!("b\"{}\"", literals.escape_ascii()),
+                    };
+                    tree.push_str(&format!("  Literal {}\n", text));
+                    literals.clear();
+                }
+                Token::AnyChar => tree.push_str("  AnyChar\n"),
+                Token::AnyString => tree.push_str("  AnyString\n"),
+                Token::Class {
+                    negated, members, ..
+                } => {
+                    tree.push_str(if *negated {
+                        "  Class negated\n"
+                    } else {
+                        "  Class\n"
+                    });
+                    for member in members {
+                        let line = match *member {
+                            ClassMember::Byte(b) => format!("    Byte '{}'\n", b.escape_ascii()),
+                            ClassMember::Range(start, end) => format!(
+                                "    Range '{}'-'{}'\n",
+                                start.escape_ascii(),
+                                end.escape_ascii()
+                            ),
+                        };
+                        tree.push_str(&line);
+                    }
+                }
+            }
+        }
+        tree
+    }
+
+    /// How specific this pattern is: higher scores match fewer strings.
+    ///
+    /// Each literal adds 3, each bracketed pattern 2 and each `?` 1, while `*` adds nothing.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert!(Pattern::new("src/*.rs").specificity() > Pattern::new("*.rs").specificity());
+    /// assert_eq!(Pattern::new("*").specificity(), 0);
+    /// ```
+    pub fn specificity(&self) -> usize {
+        self.tokens
+            .iter()
+            .map(|token| match token {
+                Token::Literal(_) => 3,
+                Token::Class { .. } => 2,
+                Token::AnyChar => 1,
+                Token::AnyString => 0,
+            })
+            .sum()
+    }
+
+    /// The number of bytes this pattern has allocated on the heap.
+    ///
+    /// Counts the compiled tokens, including the bitsets of bracketed patterns, the members of
+    /// bracketed patterns, and the skip tables of `*` followed by literals. Useful to bound
+    /// caches by memory instead of count.
+    pub fn heap_size(&self) -> usize {
+        let members: usize = self
+            .tokens
+            .iter()
+            .map(|token| match token {
+                Token::Class { members, .. } => members.capacity() * mem::size_of::<ClassMember>(),
+                _ => 0,
+            })
+            .sum();
+        let skips: usize = self
+            .skips
+            .iter()
+            .flatten()
+            .map(|skip| {
+                mem::size_of::<StarSkip>()
+                    + skip.exact.needle.capacity()
+                    + skip.lowered.needle.capacity()
+            })
+            .sum();
+        self.tokens.capacity() * mem::size_of::<Token>()
+            + self.prefix.capacity()
+            + members
+            + self.skips.capacity() * mem::size_of::<Option<Box<StarSkip>>>()
+            + skips
+    }
+
+    /// A hash of what the pattern matches, stable across runs and platforms.
+    ///
+    /// Computed with FNV-1a over the compiled tokens rather than the source, so spellings
+    /// compiling to the same tokens, like `a**b` and `a*b` or `[ab]` and `[ba]`, share a
+    /// fingerprint. Suitable as a key for caches persisted between runs.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert_eq!(Pattern::new("a**b").fingerprint(), Pattern::new("a*b").fingerprint());
+    /// assert_ne!(Pattern::new("a*b").fingerprint(), Pattern::new("a?b").fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let mut hash = OFFSET;
+        let mut write = |bytes: &[u8]| {
+            for &b in bytes {
+                hash = (hash ^ u64::from(b)).wrapping_mul(PRIME);
+            }
+        };
+        write(&[u8::from(self.lowered)]);
+        for token in &self.tokens {
+            match token {
+                Token::Literal(b) => write(&[0, *b]),
+                Token::AnyChar => write(&[1]),
+                Token::AnyString => write(&[2]),
+                Token::Class { negated, set, .. } => {
+                    write(&[3, u8::from(*negated)]);
+                    for word in set.exact.iter().chain(&set.folded) {
+                        write(&word.to_le_bytes());
+                    }
+                }
+            }
+        }
+        hash
+    }
+
+    /// Match a string against this pattern and measure how long matching took.
+    ///
+    /// Only the match itself is timed; the pattern is already compiled.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("m*nster");
+    /// let (matched, elapsed) = pattern.matches_timed("mønster");
+    /// assert!(matched);
+    /// println!("took {:?}", elapsed);
+    /// ```
+    pub fn matches_timed(&self, string: &str) -> (bool, Duration) {
+        let start = Instant::now();
+        let matched = self.matches(string);
+        (matched, start.elapsed())
+    }
+
+    /// Match a string against this pattern, with the given case sensitivity.
+    ///
+    /// Patterns created with [`Pattern::new_ci`] ignore `case` and always match
+    /// case-insensitively.
+    pub fn matches_case(&self, string: &str, case: Case) -> bool {
+        let fold = match case {
+            _ if self.lowered => Fold::String,
+            Case::Sensitive => Fold::None,
+            Case::Insensitive => Fold::Both,
+        };
+        self.match_unlimited(string.as_bytes(), fold)
+    }
+
+    /// Match an ASCII string against an ASCII pattern, with the given case sensitivity.
+    ///
+    /// Returns [`NotAscii`] without matching if the pattern or the string contain any
+    /// non-ASCII byte. Otherwise matches like [`Pattern::matches_case`], but matching
+    /// case-insensitively lowercases the string once up front instead of folding every
+    /// comparison, which lets a `*` jump straight to the literals following it.
+    ///
+    /// ```
+    /// # use moenster::{Case, NotAscii, Pattern};
+    /// let pattern = Pattern::new("m*NSTER");
+    /// assert_eq!(pattern.matches_case_ascii_only_fast("monster", Case::Insensitive), Ok(true));
+    /// assert_eq!(pattern.matches_case_ascii_only_fast("mønster", Case::Insensitive), Err(NotAscii));
+    /// ```
+    pub fn matches_case_ascii_only_fast(&self, string: &str, case: Case) -> Result<bool, NotAscii> {
+        if !string.is_ascii() || !self.is_ascii() {
+            return Err(NotAscii);
+        }
+        if self.lowered || case == Case::Sensitive {
+            return Ok(self.matches_case(string, case));
+        }
+
+        // Short strings are lowercased on the stack, to not allocate for every match.
+        let mut buf = [0; ASCII_STACK_LEN];
+        let mut heap = Vec::new();
+        let lowered = if string.len() <= ASCII_STACK_LEN {
+            &mut buf[..string.len()]
+        } else {
+            heap.resize(string.len(), 0);
+            &mut heap[..]
+        };
+        lowered.copy_from_slice(string.as_bytes());
+        lowered.make_ascii_lowercase();
+        Ok(self.match_unlimited(lowered, Fold::Pattern))
+    }
+
+    /// Whether all literals and classes of the pattern only mention ASCII bytes.
+    fn is_ascii(&self) -> bool {
+        self.tokens.iter().all(|token| match token {
+            Token::Literal(b) => b.is_ascii(),
+            Token::AnyChar | Token::AnyString => true,
+            Token::Class { members, .. } => members.iter().all(|member| match *member {
+                ClassMember::Byte(b) => b.is_ascii(),
+                ClassMember::Range(_, end) => end.is_ascii(),
+            }),
+        })
+    }
+
+    /// Match a string and write the byte range matched by each `*` into `out`, without
+    /// allocating.
+    ///
+    /// Returns the number of ranges written, or `None` if the string doesn't match. `out`
+    /// needs room for [`Pattern::capture_count`] ranges to receive all of them; if it is
+    /// shorter, only the ranges of the first stars are written. Each `*` matches as little as
+    /// it can, like in [`tokenize`](crate::tokenize).
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("*.tar.*");
+    /// let mut out = [0..0, 0..0];
+    /// assert_eq!(pattern.captures_ranges("logs.tar.gz", &mut out), Some(2));
+    /// assert_eq!(out, [0..4, 9..11]);
+    /// ```
+    pub fn captures_ranges(&self, string: &str, out: &mut [Range<usize>]) -> Option<usize> {
+        let string = string.as_bytes();
+        if !self.matches_bytes(string) {
+            return None;
+        }
+        star_spans(&self.tokens, string, 0, self.fold(), 0, out, &mut |_| true);
+        Some(self.capture_count().min(out.len()))
+    }
+
+    /// Match a string and return the text matched by each `*`, or `None` if it doesn't match.
+    ///
+    /// Each `*` matches as little as it can, like with [`Pattern::captures_ranges`], which
+    /// finds the same ranges without allocating.
+    pub fn captures<'s>(&self, string: &'s str) -> Option<Captures<'s>> {
+        let mut ranges = vec![0..0; self.capture_count()];
+        self.captures_ranges(string, &mut ranges)?;
+        Some(Captures::new(string, ranges))
+    }
+
+    /// Match a byte string against this pattern.
+    ///
+    /// The string doesn't need to be valid UTF-8.
+    pub fn matches_bytes(&self, string: &[u8]) -> bool {
+        self.match_unlimited(string, self.fold())
+    }
+
+    /// Match a string against this pattern, comparing bytes with `eq`.
+    ///
+    /// `eq(pattern_byte, string_byte)` decides whether a literal matches a byte of the string.
+    /// A bracketed pattern matches if `eq` holds for any of its bytes, including every byte
+    /// of its ranges. This allows any kind of folding, beyond [`Pattern::matches_case`].
+    /// Literals of [`Pattern::new_ci`] patterns are passed lowercased.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("m[aeiou]*0");
+    /// let eq = |p: u8, c: u8| p == c || (p, c) == (b'0', b'O');
+    /// assert!(pattern.matches_by("monstErO", eq));
+    /// ```
+    pub fn matches_by<F: Fn(u8, u8) -> bool>(&self, string: &str, eq: F) -> bool {
+        let matches = |token: &Token, c| token.matches_by(c, &eq);
+        match_tokens(
+            &self.tokens,
+            string.as_bytes(),
+            &matches,
+            Search::Scan,
+            usize::MAX,
+        )
+        .unwrap_or(false)
+    }
+
+    /// Match a string against this pattern, limiting how deep `*` backtracking may nest.
+    ///
+    /// Every `*` that needs to try several positions adds one level of nesting.
+    /// Returns [`DepthExceeded`] instead of matching further once more than `max_depth` levels
+    /// would be needed, which protects against overflowing the stack with untrusted patterns.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("*a*b*c");
+    /// assert_eq!(pattern.matches_with_limit("xaxbxc", 3), Ok(true));
+    /// assert!(pattern.matches_with_limit("xaxbxc", 2).is_err());
+    /// ```
+    pub fn matches_with_limit(
+        &self,
+        string: &str,
+        max_depth: usize,
+    ) -> Result<bool, DepthExceeded> {
+        self.match_with(string.as_bytes(), self.fold(), max_depth)
+    }
+
+    /// Match a byte string from the `bstr` crate against this pattern.
+    ///
+    /// Works like [`Pattern::matches_bytes`], so the string may contain invalid UTF-8.
+    #[cfg(feature = "bstr")]
+    pub fn matches_bstr(&self, string: &bstr::BStr) -> bool {
+        self.matches_bytes(string)
+    }
+
+    /// Find all non-overlapping occurrences of a literal-only pattern, from left to right.
+    ///
+    /// Yields the byte range of each occurrence in `haystack`. This is a plain substring search
+    /// and much faster than matching the pattern at every position.
+    ///
+    /// Returns `None` if the pattern contains any wildcard or is empty.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("ab");
+    /// let found: Vec<_> = pattern.find_literal_iter("abxabab").unwrap().collect();
+    /// assert_eq!(found, vec![0..2, 3..5, 5..7]);
+    /// assert!(Pattern::new("a*").find_literal_iter("abxabab").is_none());
+    /// ```
+    pub fn find_literal_iter<'a>(
+        &'a self,
+        haystack: &'a str,
+    ) -> Option<impl Iterator<Item = Range<usize>> + 'a> {
+        if self.tokens.is_empty() || self.prefix.len() != self.tokens.len() {
+            return None;
+        }
+
+        let needle = &self.prefix[..];
+        let lowered = self.lowered;
+        let mut pos = 0;
+        Some(iter::from_fn(move || {
+            let start = if lowered {
+                haystack.as_bytes()[pos..]
+                    .windows(needle.len())
+                    .position(|window| {
+                        window
+                            .iter()
+                            .zip(needle)
+                            .all(|(c, p)| c.to_ascii_lowercase() == *p)
+                    })?
+            } else {
+                // Occurrences of a valid UTF-8 needle end on a char boundary.
+                haystack.get(pos..)?.find(str::from_utf8(needle).ok()?)?
+            };
+            let range = pos + start..pos + start + needle.len();
+            pos = range.end;
+            Some(range)
+        }))
+    }
+
+    /// List every string this pattern matches.
+    ///
+    /// Each `?` and bracketed pattern is expanded to the bytes of `alphabet` it matches,
+    /// literals are taken as they are (in both cases for [`Pattern::new_ci`] patterns).
+    /// Combinations that are not valid UTF-8 are left out.
+    ///
+    /// Returns `None` if the pattern contains a `*` and thus matches infinitely many strings.
+    /// The number of strings grows exponentially with the number of wildcards.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("a[bc]?");
+    /// assert_eq!(
+    ///     pattern.enumerate(b"bc").unwrap(),
+    ///     vec!["abb", "abc", "acb", "acc"]
+    /// );
+    /// ```
+    pub fn enumerate(&self, alphabet: &[u8]) -> Option<Vec<String>> {
+        let fold = self.fold();
+        let mut strings = vec![Vec::new()];
+
+        for token in &self.tokens {
+            let choices: Vec<u8> = match token {
+                Token::AnyString => return None,
+                Token::Literal(b) if self.lowered && b.is_ascii_lowercase() => {
+                    vec![*b, b.to_ascii_uppercase()]
+                }
+                Token::Literal(b) => vec![*b],
+                token => alphabet
+                    .iter()
+                    .copied()
+                    .filter(|&c| token.matches_byte(c, fold))
+                    .collect(),
+            };
+
+            strings = strings
+                .iter()
+                .flat_map(|prefix| {
+                    choices.iter().map(move |&c| {
+                        let mut string = prefix.clone();
+                        string.push(c);
+                        string
+                    })
+                })
+                .collect();
+        }
+
+        Some(
+            strings
+                .into_iter()
+                .filter_map(|string| String::from_utf8(string).ok())
+                .collect(),
+        )
+    }
+
+    /// Bytes that appear in every string matching this pattern, in pattern order.
+    ///
+    /// These come from literals and bracketed patterns of a single byte. Checking for them
+    /// first is a cheap way to rule out strings before matching them.
+    /// For [`Pattern::new_ci`] patterns letters are left out, as they match in either case;
+    /// the same applies to letters when matching with [`Case::Insensitive`].
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert_eq!(Pattern::new("a*b*c").required_bytes(), b"abc");
+    /// assert_eq!(Pattern::new("[ab]?*").required_bytes(), b"");
+    /// ```
+    pub fn required_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for token in &self.tokens {
+            let b = match token {
+                Token::Literal(b) => *b,
+                Token::Class {
+                    negated: false,
+                    set,
+                    ..
+                } => match set.single() {
+                    Some(b) => b,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let either_case = self.lowered && b.is_ascii_alphabetic();
+            if !either_case && !bytes.contains(&b) {
+                bytes.push(b);
+            }
+        }
+        bytes
+    }
+
+    /// Returns true if no string can ever match this pattern.
+    ///
+    /// This is the case if the pattern contains a bracketed pattern matching no byte at all,
+    /// like the empty `[]`.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert!(Pattern::new("a[]b").is_unsatisfiable());
+    /// assert!(!Pattern::new("a*b").is_unsatisfiable());
+    /// ```
+    pub fn is_unsatisfiable(&self) -> bool {
+        let fold = self.fold();
+        self.tokens
+            .iter()
+            .any(|token| (0..=255).all(|c| !token.matches_byte(c, fold)))
+    }
+
+    /// Check whether this pattern matches every string `other` matches.
+    ///
+    /// The check is conservative: it compares the patterns element by element and can miss
+    /// some unusual cases where the match sets are still contained in each other, but it never
+    /// returns `true` wrongly.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert!(Pattern::new("*.rs").subsumes(&Pattern::new("src/*.rs")));
+    /// assert!(!Pattern::new("src/*.rs").subsumes(&Pattern::new("*.rs")));
+    /// ```
+    pub fn subsumes(&self, other: &Pattern) -> bool {
+        let (a, b) = (&self.tokens, &other.tokens);
+        // Non-empty patterns never match the empty string.
+        if b.is_empty() {
+            return a.is_empty();
+        }
+
+        // covers[i][j]: a[i..] matches everything b[j..] matches
+        let mut covers = vec![vec![false; b.len() + 1]; a.len() + 1];
+        covers[a.len()][b.len()] = true;
+        for i in (0..a.len()).rev() {
+            for j in (0..=b.len()).rev() {
+                covers[i][j] = match (&a[i], b.get(j)) {
+                    (Token::AnyString, _) => covers[i + 1][j] || (j < b.len() && covers[i][j + 1]),
+                    (_, None) | (_, Some(Token::AnyString)) => false,
+                    (x, Some(y)) => {
+                        covers[i + 1][j + 1]
+                            && (0..=255).all(|c| {
+                                !y.matches_byte(c, other.fold()) || x.matches_byte(c, self.fold())
+                            })
+                    }
+                };
+            }
+        }
+        covers[0][0]
+    }
+
+    fn match_with(&self, string: &[u8], fold: Fold, depth: usize) -> Result<bool, DepthExceeded> {
+        if self.prefix.is_empty() {
+            let matches = |t: &Token, c| t.matches_byte(c, fold);
+            return match_tokens(&self.tokens, string, &matches, self.search(fold), depth);
+        }
+
+        // Fast path: compare the literal prefix at once, then continue with the remaining tokens.
+        let len = self.prefix.len();
+        if string.len() < len {
+            return Ok(false);
+        }
+        let (head, string) = string.split_at(len);
+        let prefix_matches = match fold {
+            Fold::None => head == &self.prefix[..],
+            // The prefix of `new_ci` patterns is already lowercase, so this folds the string only.
+            Fold::Both | Fold::String | Fold::Pattern => head.eq_ignore_ascii_case(&self.prefix),
+        };
+        if !prefix_matches {
+            return Ok(false);
+        }
+
+        let mut tokens = &self.tokens[len..];
+        if string.is_empty() {
+            while tokens.first() == Some(&Token::AnyString) {
+                tokens = &tokens[1..];
+            }
+            return Ok(tokens.is_empty());
+        }
+        let matches = |t: &Token, c| t.matches_byte(c, fold);
+        match_tokens(tokens, string, &matches, self.search(fold), depth)
+    }
+
+    /// Index of the token furthest into the pattern that failed to match, across all
+    /// backtracking attempts. See [`first_unsatisfiable_token`](crate::first_unsatisfiable_token).
+    pub(crate) fn first_unsatisfiable_token(&self, string: &[u8]) -> Option<usize> {
+        let mut furthest = 0;
+        if furthest_failure(&self.tokens, 0, string, self.fold(), &mut furthest) {
+            None
+        } else {
+            Some(furthest)
+        }
+    }
+
+    /// A pattern matching exactly the strings both this pattern and `other` match.
+    ///
+    /// Only some common shapes are handled: a pattern contained in the other one, and a
+    /// literal prefix followed by `*` together with `*` followed by a literal suffix, where
+    /// `a*` and `*b` give `a*b`. Returns `None` for everything else, and for a prefix and
+    /// suffix that could overlap, like `ab*` and `*bc`, which both match `abc`.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let intersection = Pattern::new("a*").intersection(&Pattern::new("*b"));
+    /// assert_eq!(intersection, Some(Pattern::new("a*b")));
+    /// ```
+    pub fn intersection(&self, other: &Pattern) -> Option<Pattern> {
+        if self.subsumes(other) {
+            return Some(other.clone());
+        }
+        if other.subsumes(self) {
+            return Some(self.clone());
+        }
+        if self.lowered || other.lowered {
+            return None;
+        }
+
+        let (prefix, suffix) = match (self.literal_star(), other.literal_star()) {
+            (Some((prefix, true)), Some((suffix, false)))
+            | (Some((suffix, false)), Some((prefix, true))) => (prefix, suffix),
+            _ => return None,
+        };
+        let overlaps = (1..=prefix.len().min(suffix.len()))
+            .any(|len| prefix[prefix.len() - len..] == suffix[..len]);
+        if overlaps {
+            return None;
+        }
+
+        let tokens = prefix
+            .iter()
+            .map(|&b| Token::Literal(b))
+            .chain(iter::once(Token::AnyString))
+            .chain(suffix.iter().map(|&b| Token::Literal(b)))
+            .collect();
+        Some(Pattern::from_tokens(tokens, false))
+    }
+
+    /// Returns true if the pattern is a `*` followed by at least one literal, like `*.rs`.
+    ///
+    /// Such a pattern matches exactly the strings ending with the literals (unescaped), so
+    /// callers can use a plain `ends_with` check instead; for [`Pattern::new_ci`] patterns it
+    /// needs to ignore ASCII case.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert!(Pattern::new("*.rs").is_pure_suffix());
+    /// assert!(!Pattern::new("*.r?").is_pure_suffix());
+    /// ```
+    pub fn is_pure_suffix(&self) -> bool {
+        matches!(self.literal_star(), Some((literals, false)) if !literals.is_empty())
+    }
+
+    /// Returns true if the pattern is at least one literal followed by a `*`, like `src/*`.
+    ///
+    /// Such a pattern matches exactly the strings starting with the literals, the counterpart
+    /// to [`Pattern::is_pure_suffix`] for `starts_with`.
+    pub fn is_pure_prefix(&self) -> bool {
+        matches!(self.literal_star(), Some((literals, true)) if !literals.is_empty())
+    }
+
+    /// The literals of a pattern made of literals and a single `*`, either at the end (`true`)
+    /// or at the start (`false`).
+    fn literal_star(&self) -> Option<(Vec<u8>, bool)> {
+        let literals = |tokens: &[Token]| {
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Literal(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<Option<Vec<u8>>>()
+        };
+        match &self.tokens[..] {
+            [literal @ .., Token::AnyString] => Some((literals(literal)?, true)),
+            [Token::AnyString, literal @ ..] => Some((literals(literal)?, false)),
+            _ => None,
+        }
+    }
+
+    /// Turn this pattern into one matching exactly the strings this one doesn't match.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("*.tmp").complement();
+    /// assert!(pattern.matches("notes.txt"));
+    /// assert!(!pattern.matches("notes.tmp"));
+    /// ```
+    pub fn complement(self) -> NegatedPattern {
+        NegatedPattern { pattern: self }
+    }
+
+    /// Start matching a string against this pattern, doing at most `max_steps` steps.
+    ///
+    /// Returns [`MatchProgress::Pending`] if the match isn't decided yet; pass the state to
+    /// [`Pattern::resume`] to continue. This lets a scheduler run an expensive match in small
+    /// slices. A step compares one byte or moves back to the last `*`; at least one step is
+    /// always done.
+    ///
+    /// ```
+    /// # use moenster::{MatchProgress, Pattern};
+    /// let pattern = Pattern::new("*a*b*c");
+    /// let mut progress = pattern.matches_in_steps("xaxbxc", 2);
+    /// while let MatchProgress::Pending(state) = progress {
+    ///     progress = pattern.resume("xaxbxc", state, 2);
+    /// }
+    /// assert_eq!(progress, MatchProgress::Ready(true));
+    /// ```
+    pub fn matches_in_steps(&self, string: &str, max_steps: usize) -> MatchProgress {
+        let state = MatchState {
+            token: 0,
+            pos: 0,
+            star: None,
+        };
+        self.resume(string, state, max_steps)
+    }
+
+    /// Continue a match started with [`Pattern::matches_in_steps`], doing at most `max_steps`
+    /// more steps.
+    ///
+    /// The pattern and string need to be the same as when the match was started, otherwise
+    /// the result is meaningless.
+    pub fn resume(&self, string: &str, mut state: MatchState, max_steps: usize) -> MatchProgress {
+        let (tokens, string) = (&self.tokens, string.as_bytes());
+        // Non-empty patterns never match the empty string.
+        if string.is_empty() {
+            return MatchProgress::Ready(tokens.is_empty());
+        }
+
+        for _ in 0..max_steps.max(1) {
+            let c = match string.get(state.pos) {
+                Some(&c) => c,
+                None => {
+                    let rest = tokens.get(state.token..).unwrap_or_default();
+                    let matched = rest.iter().all(|token| *token == Token::AnyString);
+                    return MatchProgress::Ready(matched);
+                }
+            };
+
+            match tokens.get(state.token) {
+                Some(Token::AnyString) => {
+                    state.token += 1;
+                    state.star = Some((state.token, state.pos));
+                    continue;
+                }
+                Some(token) if token.matches_byte(c, self.fold()) => {
+                    state.token += 1;
+                    state.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Let the last `*` take one more byte and retry from there.
+            match state.star {
+                Some((token, pos)) => {
+                    state.token = token;
+                    state.pos = pos + 1;
+                    state.star = Some((token, pos + 1));
+                }
+                None => return MatchProgress::Ready(false),
+            }
+        }
+
+        MatchProgress::Pending(state)
+    }
+
+    /// The number of bytes matched by `*` if the string matches.
+    /// See [`wildcard_consumption`](crate::wildcard_consumption).
+    pub(crate) fn wildcard_consumption(&self, string: &[u8]) -> Option<usize> {
+        if !self.matches_bytes(string) {
+            return None;
+        }
+        let single = self
+            .tokens
+            .iter()
+            .filter(|token| **token != Token::AnyString)
+            .count();
+        Some(string.len() - single)
+    }
+
+    /// The part of the string between the literals the pattern starts and ends with, if the
+    /// string matches. See [`trim_matching`](crate::trim_matching).
+    pub(crate) fn trimmed(&self, string: &[u8]) -> Option<Range<usize>> {
+        if !self.matches_bytes(string) {
+            return None;
+        }
+        let rest = &self.tokens[self.prefix.len()..];
+        let suffix = rest
+            .iter()
+            .rev()
+            .take_while(|token| matches!(token, Token::Literal(_)))
+            .count();
+        Some(self.prefix.len()..string.len() - suffix)
+    }
+
+    /// The bytes matched by each `*`, for every way the string matches, up to `limit` ways.
+    /// See [`all_captures`](crate::all_captures).
+    pub(crate) fn all_captures(&self, string: &[u8], limit: usize) -> Vec<Vec<Range<usize>>> {
+        let mut all = Vec::new();
+        if limit == 0 || !self.matches_bytes(string) {
+            return all;
+        }
+        let mut spans = vec![0..0; self.capture_count()];
+        star_spans(
+            &self.tokens,
+            string,
+            0,
+            self.fold(),
+            0,
+            &mut spans,
+            &mut |spans| {
+                all.push(spans.to_vec());
+                all.len() == limit
+            },
+        );
+        all
+    }
+
+    /// The number of captures [`Pattern::captures_ranges`] reports: one for each `*`,
+    /// counting a run of stars once.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// assert_eq!(Pattern::new("*.tar.*").capture_count(), 2);
+    /// assert_eq!(Pattern::new("a**b").capture_count(), 1);
+    /// ```
+    pub fn capture_count(&self) -> usize {
+        self.tokens
+            .iter()
+            .filter(|token| **token == Token::AnyString)
+            .count()
+    }
+
+    /// The shortest and longest byte length of each capture, one for each `*`.
+    ///
+    /// Without knowing the string, a `*` can match anything from nothing to any length, so
+    /// every capture is `(0, None)`. Use [`Pattern::capture_bounds_for`] to narrow the bounds
+    /// to a string of a given length.
+    pub fn capture_bounds(&self) -> Vec<(usize, Option<usize>)> {
+        vec![(0, None); self.capture_count()]
+    }
+
+    /// The shortest and longest byte length of each capture when matching a string of `len`
+    /// bytes, one for each `*`.
+    ///
+    /// All other elements match exactly one byte, so the captures share what is left of the
+    /// string. A single capture has exactly that length; with several, each can take anything
+    /// from none to all of it. If the string is too short to match, every capture is
+    /// `(0, Some(0))`. Useful to pre-size buffers for the captures.
+    ///
+    /// ```
+    /// # use moenster::Pattern;
+    /// let pattern = Pattern::new("id-*-end");
+    /// assert_eq!(pattern.capture_bounds_for(12), vec![(5, Some(5))]);
+    /// ```
+    pub fn capture_bounds_for(&self, len: usize) -> Vec<(usize, Option<usize>)> {
+        let count = self.capture_count();
+        let rest = len.saturating_sub(self.tokens.len() - count);
+        let min = if count == 1 { rest } else { 0 };
+        vec![(min, Some(rest)); count]
+    }
+
+    /// The spans of the string matched by each element, if the string matches.
+    /// See [`tokenize`](crate::tokenize).
+    pub(crate) fn tokenize(&self, string: &[u8]) -> Option<Vec<(TokenKind, Range<usize>)>> {
+        if !self.matches_bytes(string) {
+            return None;
+        }
+        let mut stars = vec![0..0; self.capture_count()];
+        star_spans(
+            &self.tokens,
+            string,
+            0,
+            self.fold(),
+            0,
+            &mut stars,
+            &mut |_| true,
+        );
+
+        let mut spans: Vec<(TokenKind, Range<usize>)> = Vec::new();
+        let mut stars = stars.into_iter();
+        let mut start = 0;
+        for token in &self.tokens {
+            let (kind, end) = match token {
+                Token::Literal(_) => (TokenKind::Literal, start + 1),
+                Token::AnyChar => (TokenKind::AnyChar, start + 1),
+                Token::AnyString => (TokenKind::AnyString, stars.next().map_or(start, |s| s.end)),
+                Token::Class { .. } => (TokenKind::Class, start + 1),
+            };
+            match spans.last_mut() {
+                Some((TokenKind::Literal, span)) if kind == TokenKind::Literal => span.end = end,
+                _ => spans.push((kind, start..end)),
+            }
+            start = end;
+        }
+        Some(spans)
+    }
+
+    /// Match allowing up to `max_mismatches` characters to differ.
+    /// See [`matches_with_tolerance`](crate::matches_with_tolerance).
+    pub(crate) fn matches_with_tolerance(&self, string: &[u8], max_mismatches: usize) -> bool {
+        match_tolerant(&self.tokens, string, self.fold(), max_mismatches)
+    }
+
+    fn match_unlimited(&self, string: &[u8], fold: Fold) -> bool {
+        // The stack overflows long before `usize::MAX` levels are reached.
+        self.match_with(string, fold, usize::MAX).unwrap_or(false)
+    }
+
+    /// Bytes that are interesting for this pattern: its literals and the bytes its classes
+    /// mention.
+    pub(crate) fn interesting_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(b) => bytes.push(*b),
+                Token::Class { members, .. } => {
+                    for member in members {
+                        match *member {
+                            ClassMember::Byte(b) => bytes.push(b),
+                            ClassMember::Range(start, end) => bytes.extend(&[start, end]),
+                        }
+                    }
+                }
+                Token::AnyChar | Token::AnyString => {}
+            }
+        }
+        bytes
+    }
+
+    /// Up to `limit` strings following the structure of this pattern.
+    ///
+    /// Wildcards are filled with bytes from `alphabet`: `?` and classes with a single matching
+    /// byte, `*` with nothing or a single byte. The samples are built to match the pattern,
+    /// except for the empty string, which non-empty patterns never match.
+    pub(crate) fn samples(&self, alphabet: &[u8], limit: usize) -> Vec<Vec<u8>> {
+        let mut samples = vec![Vec::new()];
+
+        for token in &self.tokens {
+            let choices: Vec<Option<u8>> = match token {
+                Token::Literal(b) => vec![Some(*b)],
+                Token::AnyString => std::iter::once(None)
+                    .chain(alphabet.iter().copied().map(Some))
+                    .collect(),
+                Token::AnyChar => alphabet.iter().copied().map(Some).collect(),
+                token => alphabet
+                    .iter()
+                    .copied()
+                    .filter(|&c| token.matches_byte(c, self.fold()))
+                    .map(Some)
+                    .collect(),
+            };
+
+            samples = samples
+                .iter()
+                .flat_map(|prefix| {
+                    choices.iter().map(move |c| {
+                        let mut sample = prefix.clone();
+                        sample.extend(c);
+                        sample
+                    })
+                })
+                .take(limit)
+                .collect();
+        }
+
+        samples
+    }
+
+    fn fold(&self) -> Fold {
+        if self.lowered {
+            Fold::String
+        } else {
+            Fold::None
+        }
+    }
+}
+
+/// Assembles a [`Pattern`] in code instead of parsing it from a string.
+///
+/// Literals are taken as they are, so nothing needs to be escaped.
+///
+/// ```
+/// # use moenster::PatternBuilder;
+/// let pattern = PatternBuilder::new()
+///     .literal("[a]")
+///     .any()
+///     .class(false, &[b'0'..=b'9'])
+///     .build();
+/// assert!(pattern.matches("[a] 7"));
+/// assert!(!pattern.matches("a 7"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PatternBuilder {
+    tokens: Vec<Token>,
+}
+
+impl PatternBuilder {
+    /// Start building an empty pattern.
+    pub fn new() -> PatternBuilder {
+        PatternBuilder::default()
+    }
+
+    /// Match the text exactly.
+    pub fn literal(mut self, text: &str) -> PatternBuilder {
+        self.tokens.extend(text.bytes().map(Token::Literal));
+        self
+    }
+
+    /// Match any single byte, like `?`.
+    pub fn any_char(mut self) -> PatternBuilder {
+        self.tokens.push(Token::AnyChar);
+        self
+    }
+
+    /// Match any number of bytes, like `*`.
+    pub fn any(mut self) -> PatternBuilder {
+        // Consecutive stars match the same as a single one.
+        if self.tokens.last() != Some(&Token::AnyString) {
+            self.tokens.push(Token::AnyString);
+        }
+        self
+    }
+
+    /// Match a single byte within (or, if `negated`, outside) any of the ranges, like `[...]`.
+    ///
+    /// Use a range like `b'a'..=b'a'` for a single byte. Empty ranges match nothing.
+    pub fn class(mut self, negated: bool, members: &[RangeInclusive<u8>]) -> PatternBuilder {
+        let members = members
+            .iter()
+            .filter(|range| !range.is_empty())
+            .map(|range| match (*range.start(), *range.end()) {
+                (start, end) if start == end => ClassMember::Byte(start),
+                (start, end) => ClassMember::Range(start, end),
+            })
+            .collect();
+        self.tokens.push(Token::class(negated, members));
+        self
+    }
+
+    /// Compile the pattern.
+    pub fn build(self) -> Pattern {
+        Pattern::from_tokens(self.tokens, false)
+    }
+}
+
+impl Matcher for Pattern {
+    fn matches(&self, string: &str) -> bool {
+        Pattern::matches(self, string)
+    }
+
+    fn specificity(&self) -> usize {
+        Pattern::specificity(self)
+    }
+}
+
+/// Compares by matching: a pattern equals every string it matches, not its own source.
+///
+/// ```
+/// # use moenster::Pattern;
+/// let pattern = Pattern::new("*.rs");
+/// assert!(pattern == "main.rs");
+/// assert!(pattern != "*.toml");
+/// ```
+impl PartialEq<str> for Pattern {
+    fn eq(&self, string: &str) -> bool {
+        self.matches(string)
+    }
+}
+
+/// Compares by matching, like `PartialEq<str>`.
+impl PartialEq<&str> for Pattern {
+    fn eq(&self, string: &&str) -> bool {
+        self.matches(string)
+    }
+}
+
+impl TryFrom<&[u8]> for Pattern {
+    type Error = FromBytesError;
+
+    /// Compile a pattern from bytes, which need to be valid UTF-8 and a well-formed pattern.
+    fn try_from(pattern: &[u8]) -> Result<Self, Self::Error> {
+        let pattern = str::from_utf8(pattern)?;
+        Ok(Pattern::try_new(pattern)?)
+    }
+}
+
+impl Token {
+    /// Whether a single-byte token matches the byte `c`.
+    ///
+    /// `AnyString` is handled by the matcher itself and matches any byte here.
+    fn matches_byte(&self, c: u8, fold: Fold) -> bool {
+        match self {
+            Token::AnyString | Token::AnyChar => true,
+            Token::Literal(b) => {
+                let (p, c) = fold.apply(*b, c);
+                p == c
+            }
+            Token::Class { negated, set, .. } => set.contains(c, fold) != *negated,
+        }
+    }
+
+    /// Whether a single-byte token matches the byte `c`, comparing bytes with `eq`.
+    fn matches_by<F: Fn(u8, u8) -> bool>(&self, c: u8, eq: &F) -> bool {
+        match self {
+            Token::AnyString | Token::AnyChar => true,
+            Token::Literal(b) => eq(*b, c),
+            Token::Class {
+                negated, members, ..
+            } => {
+                let matched = members.iter().any(|member| match *member {
+                    ClassMember::Byte(b) => eq(b, c),
+                    ClassMember::Range(start, end) => (start..=end).any(|b| eq(b, c)),
+                });
+                matched != *negated
+            }
+        }
+    }
+
+    fn class(negated: bool, members: Vec<ClassMember>) -> Token {
+        let set = ClassSet::new(&members);
+        Token::Class {
+            negated,
+            members,
+            set,
+        }
+    }
+}
+
+/// The result of matching in steps with [`Pattern::matches_in_steps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchProgress {
+    /// The match is not decided yet; pass the state to [`Pattern::resume`].
+    Pending(MatchState),
+    /// The match is done, with whether the string matched.
+    Ready(bool),
+}
+
+/// How far a match done in steps got. See [`Pattern::resume`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchState {
+    /// The next token to match.
+    token: usize,
+    /// The next byte of the string to match.
+    pos: usize,
+    /// The token after the last `*` seen and the position that `*` currently runs up to.
+    star: Option<(usize, usize)>,
+}
+
+/// The kind of pattern element that matched a span of a string, returned by
+/// [`tokenize`](crate::tokenize).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A run of literal characters.
+    Literal,
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyString,
+    /// `[...]`
+    Class,
+}
+
+/// A pattern with inverted matching, created by [`Pattern::complement`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegatedPattern {
+    pattern: Pattern,
+}
+
+impl NegatedPattern {
+    /// Match a string against this pattern.
+    ///
+    /// Returns true if the wrapped pattern does not match the string.
+    pub fn matches(&self, string: &str) -> bool {
+        !self.pattern.matches(string)
+    }
+
+    /// Unwrap the original pattern.
+    pub fn into_inner(self) -> Pattern {
+        self.pattern
+    }
+}
+
+impl Matcher for NegatedPattern {
+    fn matches(&self, string: &str) -> bool {
+        NegatedPattern::matches(self, string)
+    }
+}
+
+/// Which side of a comparison needs to be lowercased.
+#[derive(Copy, Clone)]
+enum Fold {
+    None,
+    Both,
+    /// The pattern is already lowercased.
+    String,
+    /// The string is already lowercased.
+    Pattern,
+}
+
+impl Fold {
+    fn apply(self, pattern: u8, c: u8) -> (u8, u8) {
+        match self {
+            Fold::None => (pattern, c),
+            Fold::Both => (pattern.to_ascii_lowercase(), c.to_ascii_lowercase()),
+            Fold::String => (pattern, c.to_ascii_lowercase()),
+            Fold::Pattern => (pattern.to_ascii_lowercase(), c),
+        }
+    }
+}
+
+/// Parse a pattern into tokens.
+///
+/// Malformed patterns are still parsed completely, all problems found are returned alongside.
+fn parse(mut pattern: &[u8]) -> (Vec<Token>, Vec<PatternError>) {
+    let len = pattern.len();
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    while !pattern.is_empty() {
+        let position = len - pattern.len();
+        match pattern[0] {
+            b'*' => {
+                // Consecutive stars match the same as a single one.
+                if tokens.last() != Some(&Token::AnyString) {
+                    tokens.push(Token::AnyString);
+                }
+            }
+            b'?' => tokens.push(Token::AnyChar),
+            b'[' => {
+                pattern = &pattern[1..];
+                let negated = !pattern.is_empty() && pattern[0] == b'^';
+                if negated {
+                    pattern = &pattern[1..];
+                }
+                let mut members = Vec::new();
+                loop {
+                    if pattern.is_empty() {
+                        errors.push(PatternError::UnclosedBracket { position });
+                        break;
+                    } else if pattern[0] == b'\\' && pattern.len() >= 2 {
+                        pattern = &pattern[1..];
+                        members.push(ClassMember::Byte(pattern[0]));
+                    } else if pattern[0] == b']' {
+                        if members.is_empty() && !negated {
+                            errors.push(PatternError::EmptyBracket { position });
+                        }
+                        break;
+                    } else if pattern.len() >= 3 && pattern[1] == b'-' {
+                        let start = pattern[0].min(pattern[2]);
+                        let end = pattern[0].max(pattern[2]);
+                        members.push(ClassMember::Range(start, end));
+                        pattern = &pattern[2..];
+                    } else {
+                        members.push(ClassMember::Byte(pattern[0]));
+                    }
+                    pattern = &pattern[1..];
+                }
+                tokens.push(Token::class(negated, members));
+            }
+            b'\\' if pattern.len() >= 2 && shorthand(pattern[1]).is_some() => {
+                pattern = &pattern[1..];
+                tokens.extend(shorthand(pattern[0]));
+            }
+            _ => {
+                if pattern[0] == b'\\' {
+                    if pattern.len() >= 2 {
+                        pattern = &pattern[1..];
+                    } else {
+                        errors.push(PatternError::TrailingEscape);
+                    }
+                }
+                tokens.push(Token::Literal(pattern[0]));
+            }
+        }
+
+        // An unclosed bracket runs up to the end of the pattern.
+        if !pattern.is_empty() {
+            pattern = &pattern[1..];
+        }
+    }
+
+    (tokens, errors)
+}
+
+/// The class token for the shorthand `\<class>`, like `\d`.
+fn shorthand(class: u8) -> Option<Token> {
+    let members = match class.to_ascii_lowercase() {
+        b'd' => vec![ClassMember::Range(b'0', b'9')],
+        b'w' => vec![
+            ClassMember::Range(b'a', b'z'),
+            ClassMember::Range(b'A', b'Z'),
+            ClassMember::Range(b'0', b'9'),
+            ClassMember::Byte(b'_'),
+        ],
+        b's' => b" \t\n\x0C\r"
+            .iter()
+            .map(|&b| ClassMember::Byte(b))
+            .collect(),
+        _ => return None,
+    };
+    Some(Token::class(class.is_ascii_uppercase(), members))
+}
+
+/// Match like `match_tokens`, but let up to `mismatches` single-byte tokens fail.
+fn match_tolerant(
+    mut tokens: &[Token],
+    mut string: &[u8],
+    fold: Fold,
+    mut mismatches: usize,
+) -> bool {
+    while !tokens.is_empty() && !string.is_empty() {
+        match &tokens[0] {
+            Token::AnyString => {
+                if tokens.len() == 1 {
+                    return true;
+                }
+
+                while !string.is_empty() {
+                    if match_tolerant(&tokens[1..], string, fold, mismatches) {
+                        return true;
+                    }
+                    string = &string[1..];
+                }
+
+                return false;
+            }
+            token => {
+                if !token.matches_byte(string[0], fold) {
+                    if mismatches == 0 {
+                        return false;
+                    }
+                    mismatches -= 1;
+                }
+            }
+        }
+
+        tokens = &tokens[1..];
+        string = &string[1..];
+        if string.is_empty() {
+            while tokens.first() == Some(&Token::AnyString) {
+                tokens = &tokens[1..];
+            }
+            break;
+        }
+    }
+
+    tokens.is_empty() && string.is_empty()
+}
+
+/// Match like `match_tokens`, calling `visit` with the span of each `*` for every way the
+/// string matches, until `visit` returns true.
+///
+/// `*` try the shortest span first. Spans of stars after the end of `spans` aren't recorded.
+fn star_spans<F: FnMut(&[Range<usize>]) -> bool>(
+    tokens: &[Token],
+    string: &[u8],
+    pos: usize,
+    fold: Fold,
+    star: usize,
+    spans: &mut [Range<usize>],
+    visit: &mut F,
+) -> bool {
+    match tokens.split_first() {
+        None => pos == string.len() && visit(spans),
+        Some((Token::AnyString, rest)) => (pos..=string.len()).any(|end| {
+            if let Some(span) = spans.get_mut(star) {
+                *span = pos..end;
+            }
+            star_spans(rest, string, end, fold, star + 1, spans, visit)
+        }),
+        Some((token, rest)) => {
+            pos < string.len()
+                && token.matches_byte(string[pos], fold)
+                && star_spans(rest, string, pos + 1, fold, star, spans, visit)
+        }
+    }
+}
+
+/// Match like `match_tokens`, recording the index of the furthest token that failed.
+fn furthest_failure(
+    tokens: &[Token],
+    mut i: usize,
+    mut string: &[u8],
+    fold: Fold,
+    furthest: &mut usize,
+) -> bool {
+    while i < tokens.len() && !string.is_empty() {
+        match &tokens[i] {
+            Token::AnyString => {
+                if i == tokens.len() - 1 {
+                    return true;
+                }
+
+                while !string.is_empty() {
+                    if furthest_failure(tokens, i + 1, string, fold, furthest) {
+                        return true;
+                    }
+                    string = &string[1..];
+                }
+
+                return false;
+            }
+            token => {
+                if !token.matches_byte(string[0], fold) {
+                    *furthest = (*furthest).max(i);
+                    return false;
+                }
+            }
+        }
+
+        i += 1;
+        string = &string[1..];
+        if string.is_empty() {
+            while tokens.get(i) == Some(&Token::AnyString) {
+                i += 1;
+            }
+            break;
+        }
+    }
+
+    if i == tokens.len() && string.is_empty() {
+        return true;
+    }
+    *furthest = (*furthest).max(i);
+    false
+}
+
+/// Match the tokens against the string, with `matches` deciding whether a single-byte token
+/// matches a byte.
+///
+/// Unless `search` is [`Search::Scan`], a `*` followed by a long run of literals jumps
+/// straight to the next occurrence of that run.
+fn match_tokens<F: Fn(&Token, u8) -> bool>(
+    mut tokens: &[Token],
+    mut string: &[u8],
+    matches: &F,
+    search: Search<'_>,
+    depth: usize,
+) -> Result<bool, DepthExceeded> {
+    while !tokens.is_empty() && !string.is_empty() {
+        match &tokens[0] {
+            Token::AnyString => {
+                if tokens.len() == 1 {
+                    return Ok(true);
+                }
+                if depth == 0 {
+                    return Err(DepthExceeded);
+                }
+
+                // The skip tables are aligned with the end of the tokens, which are matched from
+                // the back.
+                let skip = match search {
+                    Search::Scan => None,
+                    Search::Exact(skips) => skips[skips.len() - tokens.len()]
+                        .as_ref()
+                        .map(|skip| &skip.exact),
+                    Search::Lowercase(skips) => skips[skips.len() - tokens.len()]
+                        .as_ref()
+                        .map(|skip| &skip.lowered),
+                };
+                // Searching only pays off for longer strings.
+                if let Some(skip) = skip.filter(|_| string.len() >= SKIP_MIN_STRING) {
+                    while let Some(found) = skip.find(string) {
+                        string = &string[found..];
+                        if match_tokens(&tokens[1..], string, matches, search, depth - 1)? {
+                            return Ok(true);
+                        }
+                        string = &string[1..];
+                    }
+                    return Ok(false);
+                }
+
+                while !string.is_empty() {
+                    if match_tokens(&tokens[1..], string, matches, search, depth - 1)? {
+                        return Ok(true);
+                    }
+                    string = &string[1..];
+                }
+
+                return Ok(false);
+            }
+            token => {
+                if !matches(token, string[0]) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        tokens = &tokens[1..];
+        string = &string[1..];
+        if string.is_empty() {
+            while tokens.first() == Some(&Token::AnyString) {
+                tokens = &tokens[1..];
+            }
+            break;
+        }
+    }
+
+    Ok(tokens.is_empty() && string.is_empty())
+}
+
+/// The longest string [`Pattern::matches_case_ascii_only_fast`] lowercases on the stack.
+const ASCII_STACK_LEN: usize = 256;
+
+/// How a `*` followed by literals looks for where they occur in the string.
+///
+/// The skip tables are those of the pattern, one for each token.
+#[derive(Copy, Clone)]
+enum Search<'a> {
+    /// Try every position, as literals may match bytes other than themselves.
+    Scan,
+    /// Search for the literals as they are.
+    Exact(&'a [Option<Box<StarSkip>>]),
+    /// Search for the lowercased literals, as the string is already lowercased.
+    Lowercase(&'a [Option<Box<StarSkip>>]),
+}
+
+/// The skip tables of the literals following a `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StarSkip {
+    exact: SkipTable,
+    lowered: SkipTable,
+}
+
+/// The shortest run of literals after a `*` worth searching for with a [`SkipTable`].
+const SKIP_MIN_RUN: usize = 4;
+/// The shortest string worth searching with a [`SkipTable`].
+const SKIP_MIN_STRING: usize = 16;
+
+/// The literal bytes at the start of the tokens.
+fn literal_run(tokens: &[Token]) -> Vec<u8> {
+    tokens
+        .iter()
+        .map_while(|token| match token {
+            Token::Literal(b) => Some(*b),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Finds a needle with the Boyer-Moore-Horspool algorithm.
+#[derive(Clone, PartialEq, Eq)]
+struct SkipTable {
+    needle: Vec<u8>,
+    /// How far the needle can move ahead, depending on the last byte it was compared with.
+    shift: [usize; 256],
+}
+
+impl SkipTable {
+    fn new(needle: Vec<u8>) -> SkipTable {
+        let mut shift = [needle.len(); 256];
+        for (i, &b) in needle.iter().enumerate().take(needle.len() - 1) {
+            shift[usize::from(b)] = needle.len() - 1 - i;
+        }
+        SkipTable { needle, shift }
+    }
+
+    /// The position of the first occurrence of the needle in `haystack`.
+    fn find(&self, haystack: &[u8]) -> Option<usize> {
+        let len = self.needle.len();
+        let mut pos = 0;
+        while pos + len <= haystack.len() {
+            let window = &haystack[pos..pos + len];
+            if window == &self.needle[..] {
+                return Some(pos);
+            }
+            pos += self.shift[usize::from(window[len - 1])];
+        }
+        None
+    }
+}
+
+impl fmt::Debug for SkipTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkipTable")
+            .field("needle", &self.needle)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stringmatch, stringmatch_bytes, stringmatch_with_case, MatchOptions};
+
+    const PATTERNS: &[&str] = &[
+        "moenster",
+        "MoEnStEr",
+        "m*r",
+        "m**r",
+        "*",
+        "m?enster",
+        "m[oei]enster",
+        "m[^OEI]enster",
+        "m[N-P]enster",
+        "m[Z-a]enster",
+        "m[0-9p-n_]enster",
+        "m[\\O]enster",
+        "m[n-p",
+        "moenste\\R",
+        "m*[A-Z]ster*",
+        "m\\w\\wnster",
+        "m\\W\\Dnster",
+        "m*\\s*",
+        "m*\\S",
+    ];
+    const STRINGS: &[&str] = &[
+        "moenster", "MOENSTER", "mOeNsTeR", "mo", "m_enster", "m-4nster", "m ", "",
+    ];
+
+    #[test]
+    fn same_as_stringmatch() {
+        for pattern in PATTERNS {
+            let compiled = Pattern::new(pattern);
+            for string in STRINGS {
+                assert_eq!(
+                    compiled.matches(string),
+                    stringmatch(pattern, string),
+                    "{:?} against {:?}",
+                    pattern,
+                    string
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn new_ci_same_as_insensitive() {
+        for pattern in PATTERNS {
+            let generic = Pattern::new(pattern);
+            let lowered = Pattern::new_ci(pattern);
+            for string in STRINGS {
+                let expected = stringmatch_with_case(pattern, string, Case::Insensitive);
+                assert_eq!(generic.matches_case(string, Case::Insensitive), expected);
+                assert_eq!(
+                    lowered.matches(string),
+                    expected,
+                    "{:?} against {:?}",
+                    pattern,
+                    string
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn class_every_byte() {
+        for pattern in &[
+            "[a-z]",
+            "[^A-Z_]",
+            "[Z-a]",
+            "[\\]x\\-]",
+            "[\x00-\x1f\x7f]",
+            "\\W",
+        ] {
+            let compiled = Pattern::new(pattern);
+            let lowered = Pattern::new_ci(pattern);
+            for c in 0..=255u8 {
+                let string = [c];
+                assert_eq!(
+                    compiled.matches_bytes(&string),
+                    stringmatch_bytes(
+                        pattern.as_bytes(),
+                        &string,
+                        &MatchOptions::default(),
+                        usize::MAX
+                    ),
+                    "{:?} against {:?}",
+                    pattern,
+                    c
+                );
+                let insensitive = MatchOptions {
+                    case: Case::Insensitive,
+                    ..MatchOptions::default()
+                };
+                let expected =
+                    stringmatch_bytes(pattern.as_bytes(), &string, &insensitive, usize::MAX);
+                assert_eq!(lowered.matches_bytes(&string), expected);
+                assert_eq!(compiled.match_unlimited(&string, Fold::Both), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn try_new() {
+        assert!(Pattern::try_new("m*[a-z]?\\[\\]").is_ok());
+        assert_eq!(
+            Pattern::try_new("moe[nst").unwrap_err(),
+            PatternError::UnclosedBracket { position: 3 }
+        );
+        assert_eq!(
+            Pattern::try_new("m[^").unwrap_err(),
+            PatternError::UnclosedBracket { position: 1 }
+        );
+        assert_eq!(
+            Pattern::try_new("moenster\\").unwrap_err(),
+            PatternError::TrailingEscape
+        );
+        assert!(Pattern::new("m[n-p").matches("mo"));
+    }
+
+    #[test]
+    fn strict_empty_bracket() {
+        assert_eq!(
+            Pattern::try_new_strict("a[]b").unwrap_err(),
+            PatternError::EmptyBracket { position: 1 }
+        );
+        assert!(Pattern::try_new_strict("a[^]b").is_ok());
+        assert!(Pattern::try_new_strict("a[\\]]b").is_ok());
+        assert_eq!(
+            Pattern::try_new_strict("a[b").unwrap_err(),
+            PatternError::UnclosedBracket { position: 1 }
+        );
+
+        let lenient = Pattern::try_new("a[]b").unwrap();
+        assert!(!lenient.matches("ab"));
+        assert!(!lenient.matches("a]b"));
+        assert!(!Pattern::new("a[]b").matches("axb"));
+    }
+
+    #[test]
+    fn try_from_bytes() {
+        let pattern = Pattern::try_from(&b"m*nster"[..]).unwrap();
+        assert!(pattern.matches("mønster"));
+
+        match Pattern::try_from(&b"m\xF8nster"[..]) {
+            Err(FromBytesError::Utf8(_)) => {}
+            other => panic!("expected UTF-8 error, got {:?}", other),
+        }
+        assert_eq!(
+            Pattern::try_from(&b"m[a-z"[..]),
+            Err(FromBytesError::Pattern(PatternError::UnclosedBracket {
+                position: 1
+            }))
+        );
+    }
+
+    #[test]
+    fn from_bytes_lenient() {
+        let pattern = Pattern::from_bytes_lenient(b"m\xF8*");
+        assert!(pattern.matches_bytes(b"m\xF8nster"));
+        assert!(!pattern.matches_bytes(b"moenster"));
+    }
+
+    #[test]
+    fn literal_prefix() {
+        let pattern = Pattern::new("moenster*");
+        assert!(pattern.matches("moenster"));
+        assert!(pattern.matches("moenster.rs"));
+        assert!(!pattern.matches("moenste"));
+        assert!(!pattern.matches("moenstEr.rs"));
+        assert!(Pattern::new_ci("MOENSTER*").matches("moenstEr.rs"));
+        assert!(Pattern::new("moenster*").matches_case("MOENSTER.rs", Case::Insensitive));
+
+        assert!(Pattern::new("moenster").matches("moenster"));
+        assert!(!Pattern::new("moenster").matches("moenster.rs"));
+        assert!(Pattern::new("moe*r?").matches("moenster!"));
+        assert!(!Pattern::new("moe?").matches("moe"));
+    }
+
+    #[test]
+    fn complement() {
+        let pattern = Pattern::new("m*r").complement();
+        assert!(!pattern.matches("moenster"));
+        assert!(pattern.matches("moenste"));
+        assert!(pattern.matches(""));
+        assert_eq!(pattern.into_inner(), Pattern::new("m*r"));
+    }
+
+    #[test]
+    fn matches_in_steps() {
+        for pattern in PATTERNS {
+            let compiled = Pattern::new(pattern);
+            for string in STRINGS {
+                let expected = stringmatch(pattern, string);
+                let mut progress = compiled.matches_in_steps(string, 1);
+                let mut slices = 0;
+                while let MatchProgress::Pending(state) = progress {
+                    progress = compiled.resume(string, state, 1);
+                    slices += 1;
+                }
+                assert_eq!(
+                    progress,
+                    MatchProgress::Ready(expected),
+                    "{:?} against {:?}",
+                    pattern,
+                    string
+                );
+                assert_eq!(
+                    compiled.matches_in_steps(string, usize::MAX),
+                    MatchProgress::Ready(expected)
+                );
+                assert!(slices <= string.len() * (pattern.len() + 1));
+            }
+        }
+    }
+
+    #[test]
+    fn star_skips_to_literal_run() {
+        let haystack = format!("{}verylongliteral{}", "verylong".repeat(20), "x".repeat(70));
+        for pattern in &[
+            "*verylongliteral*",
+            "*verylongliteral",
+            "*long*literal?x*",
+            "verylong*longverylongliteral*x",
+            "*literalx*xxxx[x]",
+            "*literaly*",
+            "*xxxxxxxxxy",
+        ] {
+            assert_eq!(
+                Pattern::new(pattern).matches(&haystack),
+                stringmatch(pattern, &haystack),
+                "{:?}",
+                pattern
+            );
+            assert_eq!(
+                Pattern::new(pattern).matches_by(&haystack, |p, c| p == c),
+                stringmatch(pattern, &haystack),
+                "{:?}",
+                pattern
+            );
+        }
+        assert!(Pattern::new("*verylongliteral*").matches(&haystack));
+        assert!(!Pattern::new("*literaly*").matches(&haystack));
+
+        let skip = SkipTable::new(b"abcab".to_vec());
+        assert_eq!(skip.find(b"xxabcabcab"), Some(2));
+        assert_eq!(skip.find(b"abca"), None);
+        assert_eq!(skip.find(b"ababcab"), Some(2));
+    }
+
+    #[test]
+    fn debug_tree() {
+        assert_eq!(
+            Pattern::new("mø*[a-c\\]]?x\\d").debug_tree(),
+            "Pattern
+  Literal \"mø\"
+  AnyString
+  Class
+    Range 'a'-'c'
+    Byte ']'
+  AnyChar
+  Literal \"x\"
+  Class
+    Range '0'-'9'
+"
+        );
+        assert_eq!(
+            Pattern::from_bytes_lenient(b"\xff?").debug_tree(),
+            "Pattern\n  Literal b\"\\xff\"\n  AnyChar\n"
+        );
+        assert_eq!(Pattern::new("").debug_tree(), "Pattern\n");
+    }
+
+    #[test]
+    fn heap_size() {
+        let literal = Pattern::new("a");
+        let class = Pattern::new("[abcdefghijklmnopqrstuvwxyz0123456789]");
+        assert!(literal.heap_size() > 0);
+        assert!(class.heap_size() > literal.heap_size());
+        assert!(Pattern::new("aaaa").heap_size() > literal.heap_size());
+        assert_eq!(Pattern::new("").heap_size(), 0);
+        assert!(Pattern::new("*literal").heap_size() > Pattern::new("literal*").heap_size());
+    }
+
+    #[test]
+    fn ascii_only_fast() {
+        for pattern in PATTERNS {
+            let compiled = Pattern::new(pattern);
+            for string in STRINGS {
+                for &case in &[Case::Sensitive, Case::Insensitive] {
+                    let fast = compiled.matches_case_ascii_only_fast(string, case);
+                    if string.is_ascii() && pattern.is_ascii() {
+                        assert_eq!(fast, Ok(compiled.matches_case(string, case)));
+                    } else {
+                        assert_eq!(fast, Err(NotAscii));
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            Pattern::new("*").matches_case_ascii_only_fast("abcø", Case::Sensitive),
+            Err(NotAscii)
+        );
+        assert_eq!(
+            Pattern::new("[a-ø]").matches_case_ascii_only_fast("a", Case::Sensitive),
+            Err(NotAscii)
+        );
+
+        // Long enough to search for the literals after `*`, and to lowercase on the heap.
+        for len in [100, 1_000] {
+            let string = format!("{}Needle-In-Haystack{}", "Hay".repeat(len), "!".repeat(len));
+            for pattern in ["*needle-in-hay*", "*NEEDLE-in-[h]ay*", "*needle-in-hayx*"] {
+                let compiled = Pattern::new(pattern);
+                assert_eq!(
+                    compiled.matches_case_ascii_only_fast(&string, Case::Insensitive),
+                    Ok(compiled.matches_case(&string, Case::Insensitive)),
+                    "{}",
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_timed() {
+        let pattern = Pattern::new("*a*b*c");
+        let (matched, elapsed) = pattern.matches_timed("xaxbxc");
+        assert!(matched);
+        assert!(elapsed >= Duration::ZERO);
+        assert!(!pattern.matches_timed("xaxbxd").0);
+    }
+
+    #[test]
+    fn builder() {
+        let built = PatternBuilder::new()
+            .literal("a")
+            .any()
+            .class(false, &[b'b'..=b'b', b'c'..=b'c'])
+            .any_char()
+            .build();
+        let parsed = Pattern::new("a*[bc]?");
+        for string in &["abc", "ab", "axxcd", "abx", "acbb", "a*[bc]?", "bb", ""] {
+            assert_eq!(
+                built.matches(string),
+                parsed.matches(string),
+                "{:?}",
+                string
+            );
+        }
+
+        let built = PatternBuilder::new()
+            .literal("m*")
+            .any()
+            .any()
+            .class(true, &[b'a'..=b'z'])
+            .build();
+        assert_eq!(built, Pattern::new("m\\**[^a-z]"));
+        assert!(built.matches("m*nster!"));
+        assert!(!built.matches("monster!"));
+
+        assert_eq!(PatternBuilder::new().build(), Pattern::new(""));
+    }
+
+    #[test]
+    fn outlives_source() {
+        fn store(pattern: Pattern) -> Box<dyn Matcher + 'static> {
+            Box::new(pattern)
+        }
+
+        let source = String::from("m*nster");
+        let compiled = Pattern::new(&source);
+        let copy = compiled.clone_static();
+        drop(source);
+
+        let stored = store(compiled);
+        assert!(stored.matches("mønster"));
+        assert!(copy.matches("monster"));
+    }
+
+    #[test]
+    fn matches_by() {
+        let eq = |p: u8, c: u8| p == c || matches!((p, c), (b'0', b'O') | (b'O', b'0'));
+        let pattern = Pattern::new("r00t");
+        assert!(pattern.matches_by("r00t", eq));
+        assert!(pattern.matches_by("rO0t", eq));
+        assert!(pattern.matches_by("rOOt", eq));
+        assert!(!pattern.matches_by("root", eq));
+        assert!(!pattern.matches("rOOt"));
+
+        assert!(Pattern::new("v[0-3]").matches_by("vO", eq));
+        assert!(Pattern::new("v[O]*").matches_by("v0.1", eq));
+        assert!(!Pattern::new("v[^O]").matches_by("v0", eq));
+
+        for pattern in PATTERNS {
+            let compiled = Pattern::new(pattern);
+            for string in STRINGS {
+                assert_eq!(
+                    compiled.matches_by(string, |p, c| p == c),
+                    stringmatch(pattern, string)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let pattern = Pattern::new("*a*b*c");
+        assert_eq!(pattern.matches_with_limit("xaxbxc", 3), Ok(true));
+        assert_eq!(pattern.matches_with_limit("xaxbxd", 3), Ok(false));
+        assert_eq!(pattern.matches_with_limit("xaxbxc", 2), Err(DepthExceeded));
+        assert_eq!(pattern.matches_with_limit("xaxbxc", 0), Err(DepthExceeded));
+
+        // Trailing stars don't need to backtrack.
+        assert_eq!(
+            Pattern::new("moe*").matches_with_limit("moenster", 0),
+            Ok(true)
+        );
+        assert_eq!(
+            Pattern::new("moe?ster").matches_with_limit("moenster", 0),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn required_bytes() {
+        assert_eq!(Pattern::new("a*b*c").required_bytes(), b"abc");
+        assert_eq!(Pattern::new("m?nst*r").required_bytes(), b"mnstr");
+        assert_eq!(Pattern::new("[x]y[a-a][zz]\\*").required_bytes(), b"xyaz*");
+        assert_eq!(Pattern::new("a*a*b").required_bytes(), b"ab");
+        assert_eq!(Pattern::new_ci("M*8").required_bytes(), b"8");
+
+        // Classes with several members, negated classes and wildcards require nothing.
+        assert_eq!(Pattern::new("[ab][^c]?*\\d").required_bytes(), b"");
+        assert_eq!(Pattern::new("").required_bytes(), b"");
+    }
+
+    #[test]
+    fn is_unsatisfiable() {
+        assert!(Pattern::new("a[]b").is_unsatisfiable());
+        assert!(Pattern::new("*[]").is_unsatisfiable());
+        assert!(Pattern::from_bytes_lenient(b"[^\x00-\xff]").is_unsatisfiable());
+        assert!(Pattern::new_ci("x[]").is_unsatisfiable());
+
+        assert!(!Pattern::new("a*b").is_unsatisfiable());
+        assert!(!Pattern::new("").is_unsatisfiable());
+        assert!(!Pattern::new("[^]").is_unsatisfiable());
+        assert!(!Pattern::new("[\\]]").is_unsatisfiable());
+        assert!(!Pattern::new("\\d?[a-z]").is_unsatisfiable());
+    }
+
+    #[test]
+    fn intersection() {
+        let intersection = |a, b| Pattern::new(a).intersection(&Pattern::new(b));
+
+        assert_eq!(intersection("a*", "*b"), Some(Pattern::new("a*b")));
+        assert_eq!(
+            intersection("*.rs", "src/*"),
+            Some(Pattern::new("src/*.rs"))
+        );
+        assert_eq!(
+            intersection("*.rs", "src/*.rs"),
+            Some(Pattern::new("src/*.rs"))
+        );
+        assert_eq!(intersection("m*", "mø*"), Some(Pattern::new("mø*")));
+
+        // `abc` matches both, but not `ab*bc`.
+        assert_eq!(intersection("ab*", "*bc"), None);
+        assert_eq!(intersection("a?c", "*[bc]"), Some(Pattern::new("a?c")));
+        assert_eq!(intersection("a?c", "*b?"), None);
+        assert_eq!(intersection("a*", "b*"), None);
+        assert_eq!(
+            Pattern::new_ci("a*").intersection(&Pattern::new("*b")),
+            None
+        );
+
+        let a = Pattern::new("ab*");
+        let b = Pattern::new("*cd");
+        let both = a.intersection(&b).unwrap();
+        for string in &["abcd", "abxcd", "abc", "acd", "abcdcd", "cdab"] {
+            assert_eq!(both.matches(string), a.matches(string) && b.matches(string));
+        }
+    }
+
+    #[test]
+    fn capture_bounds() {
+        let middle = Pattern::new("[a-z]?-*-end");
+        assert_eq!(middle.capture_bounds(), vec![(0, None)]);
+        assert_eq!(middle.capture_bounds_for(10), vec![(3, Some(3))]);
+        assert_eq!(middle.capture_bounds_for(7), vec![(0, Some(0))]);
+        assert_eq!(middle.capture_bounds_for(3), vec![(0, Some(0))]);
+
+        let several = Pattern::new("*-**-*");
+        assert_eq!(several.capture_bounds(), vec![(0, None); 3]);
+        assert_eq!(several.capture_bounds_for(10), vec![(0, Some(8)); 3]);
+
+        assert!(Pattern::new("lib.rs").capture_bounds_for(6).is_empty());
+    }
+
+    #[test]
+    fn captures_ranges() {
+        let pattern = Pattern::new("*/*.?s");
+        let mut out = [0..0, 0..0];
+        assert_eq!(out.len(), pattern.capture_count());
+        assert_eq!(pattern.captures_ranges("src/lib.rs", &mut out), Some(2));
+        assert_eq!(out, [0..3, 4..7]);
+        assert_eq!(pattern.captures_ranges("a/b/c.js", &mut out), Some(2));
+        assert_eq!(out, [0..1, 2..5]);
+        assert_eq!(pattern.captures_ranges("src/lib.rc", &mut out), None);
+
+        let mut short = [0..0, 0..0];
+        assert_eq!(
+            pattern.captures_ranges("src/lib.rs", &mut short[..1]),
+            Some(1)
+        );
+        assert_eq!(short, [0..3, 0..0]);
+        assert_eq!(pattern.captures_ranges("src/lib.rs", &mut []), Some(0));
+
+        let literal = Pattern::new("lib.rs");
+        assert_eq!(literal.capture_count(), 0);
+        assert_eq!(literal.captures_ranges("lib.rs", &mut []), Some(0));
+    }
+
+    #[test]
+    fn eq_str() {
+        let pattern = Pattern::new("m?nster");
+        assert!(pattern == "monster");
+        assert!(pattern == *"munster");
+        assert!(pattern != "m?nster!");
+        assert!(Pattern::new("\\*") == "*");
+        assert!(Pattern::new("\\*") != "\\*");
+        assert!(Pattern::new("*") != "");
+    }
+
+    #[test]
+    fn fingerprint() {
+        let fingerprint = |pattern| Pattern::new(pattern).fingerprint();
+        assert_eq!(fingerprint("a**b"), fingerprint("a*b"));
+        assert_eq!(fingerprint("[ab]c"), fingerprint("[ba]c"));
+        assert_eq!(fingerprint("[a-c]"), fingerprint("[abc]"));
+        assert_eq!(fingerprint("\\x"), fingerprint("x"));
+
+        assert_ne!(fingerprint("a*b"), fingerprint("a?b"));
+        assert_ne!(fingerprint("a*b"), fingerprint("b*a"));
+        assert_ne!(fingerprint("[ab]"), fingerprint("[^ab]"));
+        assert_ne!(fingerprint("ab"), fingerprint("[ab]"));
+        assert_ne!(
+            Pattern::new("ab").fingerprint(),
+            Pattern::new_ci("ab").fingerprint()
+        );
+        // Pinned, so fingerprints kept across runs stay valid.
+        assert_eq!(fingerprint(""), 0xaf63_bd4c_8601_b7df);
+    }
+
+    #[test]
+    fn pure_prefix_and_suffix() {
+        let shapes = |pattern| {
+            let pattern = Pattern::new(pattern);
+            (pattern.is_pure_prefix(), pattern.is_pure_suffix())
+        };
+        assert_eq!(shapes("*.rs"), (false, true));
+        assert_eq!(shapes("**.rs"), (false, true));
+        assert_eq!(shapes("*\\*"), (false, true));
+        assert_eq!(shapes("src/*"), (true, false));
+        assert_eq!(shapes("src/**"), (true, false));
+
+        assert_eq!(shapes("*"), (false, false));
+        assert_eq!(shapes("moenster"), (false, false));
+        assert_eq!(shapes("*.r?"), (false, false));
+        assert_eq!(shapes("*.[rs]"), (false, false));
+        assert_eq!(shapes("*src*"), (false, false));
+        assert_eq!(shapes("src/*.rs"), (false, false));
+        assert_eq!(shapes(""), (false, false));
+    }
+
+    #[test]
+    fn subsumes() {
+        let subsumes = |a, b| Pattern::new(a).subsumes(&Pattern::new(b));
+        assert!(subsumes("*", "moenster"));
+        assert!(subsumes("*", "m*r"));
+        assert!(subsumes("m*", "m?enst*r"));
+        assert!(subsumes("m?enster", "m[oe]enster"));
+        assert!(subsumes("m[a-z]enster", "m[oe]enster"));
+        assert!(subsumes("*r", "*ster"));
+        assert!(subsumes("moenster", "moenster"));
+        assert!(!subsumes("m[oe]enster", "m?enster"));
+        assert!(!subsumes("m?enster", "m*enster"));
+        assert!(!subsumes("*ster", "*r"));
+        assert!(!subsumes("*", ""));
+        assert!(subsumes("", ""));
+
+        assert!(Pattern::new_ci("M*").subsumes(&Pattern::new("m*")));
+        assert!(!Pattern::new("m*").subsumes(&Pattern::new_ci("M*")));
+    }
+
+    #[test]
+    fn find_literal_iter() {
+        let find = |pattern: &Pattern, haystack| {
+            pattern
+                .find_literal_iter(haystack)
+                .map(|found| found.collect::<Vec<_>>())
+        };
+
+        let pattern = Pattern::new("mønster");
+        assert_eq!(
+            find(&pattern, "mønster, monster, mønstermønster"),
+            Some(vec![0..8, 19..27, 27..35])
+        );
+        assert_eq!(find(&pattern, "monster"), Some(vec![]));
+        assert_eq!(find(&Pattern::new("aa"), "aaaaa"), Some(vec![0..2, 2..4]));
+        assert_eq!(find(&Pattern::new("a\\*"), "a*a*"), Some(vec![0..2, 2..4]));
+        assert_eq!(
+            find(&Pattern::new_ci("ab"), "AbxaBab"),
+            Some(vec![0..2, 3..5, 5..7])
+        );
+
+        assert_eq!(find(&Pattern::new("a?"), "ab"), None);
+        assert_eq!(find(&Pattern::new("[ab]"), "ab"), None);
+        assert_eq!(find(&Pattern::new(""), "ab"), None);
+    }
+
+    #[test]
+    fn enumerate() {
+        let pattern = Pattern::new("a[bc]?");
+        assert_eq!(
+            pattern.enumerate(b"bc").unwrap(),
+            vec!["abb", "abc", "acb", "acc"]
+        );
+        assert_eq!(
+            Pattern::new("[^a]").enumerate(b"abc").unwrap(),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            Pattern::new("a?").enumerate(b"").unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(Pattern::new_ci("a").enumerate(b"").unwrap(), vec!["a", "A"]);
+        assert_eq!(Pattern::new("a*b").enumerate(b"ab"), None);
+    }
+
+    #[cfg(feature = "bstr")]
+    #[test]
+    fn matches_bstr() {
+        use bstr::ByteSlice;
+
+        let pattern = Pattern::new("m?nster*");
+        assert!(pattern.matches_bstr(b"m\xFFnster".as_bstr()));
+        assert!(pattern.matches_bstr(b"monster\xFF".as_bstr()));
+        assert!(!pattern.matches_bstr(b"m\xFF\xFFnster".as_bstr()));
+    }
+
+    #[test]
+    fn new_ci_ignores_case_argument() {
+        let pattern = Pattern::new_ci("MOENSTER");
+        assert!(pattern.matches_case("moenster", Case::Sensitive));
+        assert!(!Pattern::new("MOENSTER").matches_case("moenster", Case::Sensitive));
+    }
+}
@@ -0,0 +1,90 @@
+//! Matching binary records made of length-prefixed fields.
+
+use crate::{repeatable_len, skip_trailing_stars, stringmatch_bytes, MatchOptions};
+
+/// Match a binary record of length-prefixed fields against the specified pattern.
+///
+/// Each field of the record is framed as one length byte followed by that many bytes of
+/// content, so `b"\x02ab\x00\x01c"` holds the fields `ab`, an empty one and `c`.
+///
+/// A `?` matches one whole field: it reads the byte at the current position as a length and
+/// skips the content after it. All other elements match the raw bytes of the record, length
+/// bytes included, like with [`stringmatch`](crate::stringmatch); `*` matches any number of
+/// bytes, including none. A record that ends within a field the `?` would skip doesn't match.
+///
+/// ```
+/// # use moenster::stringmatch_lenprefixed;
+/// assert!(stringmatch_lenprefixed(b"???", b"\x02ab\x00\x01c"));
+/// assert!(stringmatch_lenprefixed(b"\x02ab*", b"\x02ab\x00\x01c"));
+/// assert!(!stringmatch_lenprefixed(b"??", b"\x02ab\x00\x01c"));
+/// ```
+pub fn stringmatch_lenprefixed(pattern: &[u8], record: &[u8]) -> bool {
+    let first = match pattern.first() {
+        Some(&first) => first,
+        None => return record.is_empty(),
+    };
+
+    match first {
+        b'*' => {
+            // A run of stars matches the same as a single one.
+            let rest = skip_trailing_stars(pattern);
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=record.len()).any(|i| stringmatch_lenprefixed(rest, &record[i..]))
+        }
+        b'?' => match record.split_first() {
+            Some((&len, content)) if content.len() >= usize::from(len) => {
+                stringmatch_lenprefixed(&pattern[1..], &content[usize::from(len)..])
+            }
+            _ => false,
+        },
+        _ => {
+            let len = repeatable_len(pattern).max(1);
+            match record.split_first() {
+                Some((c, rest)) => {
+                    let element = &pattern[..len];
+                    stringmatch_bytes(element, &[*c], &MatchOptions::default(), usize::MAX)
+                        && stringmatch_lenprefixed(&pattern[len..], rest)
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let record = b"\x03abc\x00\x05hello\x01x";
+        assert!(stringmatch_lenprefixed(b"????", record));
+        assert!(!stringmatch_lenprefixed(b"???", record));
+        assert!(!stringmatch_lenprefixed(b"?????", record));
+        assert!(stringmatch_lenprefixed(b"?\x00??", record));
+        assert!(stringmatch_lenprefixed(b"\x03abc???", record));
+        assert!(stringmatch_lenprefixed(b"\x03[a-c][a-c]c*", record));
+        assert!(stringmatch_lenprefixed(b"???\x01x", record));
+        assert!(!stringmatch_lenprefixed(b"???\x01?", record));
+        assert!(stringmatch_lenprefixed(b"*\x01x", record));
+        assert!(stringmatch_lenprefixed(b"*", b""));
+        assert!(stringmatch_lenprefixed(b"?", b"\x00"));
+
+        // The last field is cut short.
+        assert!(!stringmatch_lenprefixed(b"??", b"\x01a\x03bc"));
+        assert!(!stringmatch_lenprefixed(b"?", b""));
+    }
+
+    #[test]
+    fn many_stars() {
+        let record = [b'a'; 30];
+        let mut pattern = vec![b'*'; 30];
+        assert!(stringmatch_lenprefixed(&pattern, &record));
+        pattern.push(b'x');
+        assert!(!stringmatch_lenprefixed(&pattern, &record));
+        pattern.extend_from_slice(b"*?*");
+        assert!(!stringmatch_lenprefixed(&pattern, &record));
+    }
+}
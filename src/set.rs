@@ -0,0 +1,139 @@
+//! Matching against several patterns at once.
+
+use std::fmt;
+
+/// Anything that can be matched against a string, like a [`Pattern`](crate::Pattern).
+pub trait Matcher {
+    /// Returns true if the string matches.
+    fn matches(&self, string: &str) -> bool;
+
+    /// How specific the matcher is, higher scores for matchers matching fewer strings.
+    ///
+    /// Used to rank matches in [`PatternSet::matching_ranked`]. Defaults to 0, the score of
+    /// a pattern matching anything.
+    fn specificity(&self) -> usize {
+        0
+    }
+}
+
+/// An ordered collection of patterns.
+///
+/// Can hold any kind of [`Matcher`], so compiled and negated patterns can be mixed.
+///
+/// ```
+/// # use moenster::{Pattern, PatternSet};
+/// let mut set = PatternSet::new();
+/// set.push(Pattern::new("*.rs"));
+/// set.push(Pattern::new("*.toml"));
+/// assert!(set.is_match("Cargo.toml"));
+/// assert_eq!(set.matching("lib.rs"), vec![0]);
+/// ```
+#[derive(Default)]
+pub struct PatternSet {
+    matchers: Vec<Box<dyn Matcher + Send + Sync>>,
+}
+
+impl PatternSet {
+    /// Create an empty set.
+    pub fn new() -> PatternSet {
+        PatternSet::default()
+    }
+
+    /// Add a pattern to the end of the set.
+    pub fn push<M: Matcher + Send + Sync + 'static>(&mut self, matcher: M) {
+        self.matchers.push(Box::new(matcher));
+    }
+
+    /// The number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.matchers.len()
+    }
+
+    /// Returns true if the set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// Returns true if any pattern in the set matches the string.
+    pub fn is_match(&self, string: &str) -> bool {
+        self.matchers.iter().any(|m| m.matches(string))
+    }
+
+    /// The indices of all patterns in the set matching the string, in order.
+    pub fn matching(&self, string: &str) -> Vec<usize> {
+        self.matchers
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.matches(string))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The indices of all patterns in the set matching the string, most specific first.
+    ///
+    /// Patterns are ranked by their [`Matcher::specificity`]; ties keep the order the
+    /// patterns were added in.
+    ///
+    /// ```
+    /// # use moenster::{Pattern, PatternSet};
+    /// let mut set = PatternSet::new();
+    /// set.push(Pattern::new("*"));
+    /// set.push(Pattern::new("src/*.rs"));
+    /// set.push(Pattern::new("*.rs"));
+    /// assert_eq!(set.matching_ranked("src/lib.rs"), vec![1, 2, 0]);
+    /// ```
+    pub fn matching_ranked(&self, string: &str) -> Vec<usize> {
+        let mut matching = self.matching(string);
+        matching.sort_by_key(|&i| std::cmp::Reverse(self.matchers[i].specificity()));
+        matching
+    }
+}
+
+impl Matcher for PatternSet {
+    fn matches(&self, string: &str) -> bool {
+        self.is_match(string)
+    }
+}
+
+impl fmt::Debug for PatternSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternSet")
+            .field("len", &self.matchers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pattern;
+
+    #[test]
+    fn mixed_set() {
+        let mut set = PatternSet::new();
+        set.push(Pattern::new("*.rs"));
+        set.push(Pattern::new("test_*").complement());
+        assert_eq!(set.len(), 2);
+
+        assert_eq!(set.matching("lib.rs"), vec![0, 1]);
+        assert_eq!(set.matching("test_lib.rs"), vec![0]);
+        assert_eq!(set.matching("README.md"), vec![1]);
+        assert!(!set.is_match("test_data"));
+    }
+
+    #[test]
+    fn ranked() {
+        let mut set = PatternSet::new();
+        set.push(Pattern::new("*"));
+        set.push(Pattern::new("src/*"));
+        set.push(Pattern::new("*.rs"));
+        set.push(Pattern::new("src/lib.rs"));
+        set.push(Pattern::new("src/???.rs"));
+        set.push(Pattern::new("docs/*"));
+        set.push(Pattern::new("*.md").complement());
+
+        assert_eq!(set.matching_ranked("src/lib.rs"), vec![3, 4, 1, 2, 0, 6]);
+        assert_eq!(set.matching_ranked("src/main.rs"), vec![1, 2, 0, 6]);
+        assert_eq!(set.matching_ranked("docs/a.md"), vec![5, 0]);
+    }
+}
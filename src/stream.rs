@@ -0,0 +1,115 @@
+//! Matching records of a stream as it arrives in chunks.
+
+use crate::Pattern;
+
+/// Matches the delimited records of a stream against a pattern, chunk by chunk.
+///
+/// Records are the bytes between delimiters, without the delimiter. A record split across
+/// chunks is kept until its delimiter arrives; call [`StreamScanner::finish`] at the end of the
+/// stream to match a last record without a trailing delimiter.
+///
+/// ```
+/// # use moenster::{Pattern, StreamScanner};
+/// let mut scanner = StreamScanner::new(Pattern::new("ERROR *"), b'\n');
+/// let mut errors = Vec::new();
+/// for chunk in [&b"INFO start\nERR"[..], b"OR disk full\nINFO done"] {
+///     scanner.feed(chunk, &mut |record| errors.push(record.to_vec()));
+/// }
+/// scanner.finish(&mut |record| errors.push(record.to_vec()));
+/// assert_eq!(errors, vec![b"ERROR disk full".to_vec()]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamScanner {
+    pattern: Pattern,
+    delimiter: u8,
+    /// The start of a record whose delimiter hasn't arrived yet.
+    pending: Vec<u8>,
+}
+
+impl StreamScanner {
+    /// Create a scanner matching records separated by `delimiter`.
+    pub fn new(pattern: Pattern, delimiter: u8) -> StreamScanner {
+        StreamScanner {
+            pattern,
+            delimiter,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Feed the next chunk of the stream, calling `on_match` with each completed record that
+    /// matches.
+    pub fn feed<F: FnMut(&[u8])>(&mut self, chunk: &[u8], on_match: &mut F) {
+        let delimiter = self.delimiter;
+        let mut records = chunk.split(|&b| b == delimiter);
+        // The last piece has no delimiter after it yet.
+        let rest = records.next_back().unwrap_or_default();
+
+        for record in records {
+            if self.pending.is_empty() {
+                self.emit(record, on_match);
+            } else {
+                let mut pending = std::mem::take(&mut self.pending);
+                pending.extend_from_slice(record);
+                self.emit(&pending, on_match);
+                // Keep the allocation for the next split record.
+                pending.clear();
+                self.pending = pending;
+            }
+        }
+        self.pending.extend_from_slice(rest);
+    }
+
+    /// End the stream, calling `on_match` with the last record if it wasn't ended by a
+    /// delimiter and matches.
+    ///
+    /// Nothing is called if the stream ended with a delimiter.
+    pub fn finish<F: FnMut(&[u8])>(self, on_match: &mut F) {
+        if !self.pending.is_empty() {
+            self.emit(&self.pending, on_match);
+        }
+    }
+
+    fn emit<F: FnMut(&[u8])>(&self, record: &[u8], on_match: &mut F) {
+        if self.pattern.matches_bytes(record) {
+            on_match(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(chunks: &[&[u8]]) -> Vec<String> {
+        let mut scanner = StreamScanner::new(Pattern::new("id=?*"), b';');
+        let mut matched = Vec::new();
+        let mut on_match =
+            |record: &[u8]| matched.push(String::from_utf8(record.to_vec()).unwrap());
+        for chunk in chunks {
+            scanner.feed(chunk, &mut on_match);
+        }
+        scanner.finish(&mut on_match);
+        matched
+    }
+
+    #[test]
+    fn split_records() {
+        let stream = b"id=1;name=x;id=;id=42;;id=7";
+        let expected = vec!["id=1", "id=42", "id=7"];
+
+        assert_eq!(scan(&[stream]), expected);
+        // Every possible split into two and three chunks.
+        for i in 0..=stream.len() {
+            assert_eq!(scan(&[&stream[..i], &stream[i..]]), expected, "{}", i);
+            for j in i..=stream.len() {
+                let chunks = [&stream[..i], &stream[i..j], &stream[j..]];
+                assert_eq!(scan(&chunks), expected, "{} {}", i, j);
+            }
+        }
+        let bytes: Vec<&[u8]> = stream.chunks(1).collect();
+        assert_eq!(scan(&bytes), expected);
+
+        assert_eq!(scan(&[b"id=1;", b"id=2;"]), vec!["id=1", "id=2"]);
+        assert!(scan(&[]).is_empty());
+    }
+}
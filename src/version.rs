@@ -0,0 +1,156 @@
+//! Matching dotted version numbers.
+
+use crate::{stringmatch, PatternError};
+
+/// Match a dotted version number against the specified pattern.
+///
+/// Both the pattern and the version are split into segments at `.` and need to have the same
+/// number of segments. Each segment of the pattern is matched against the corresponding
+/// segment of the version:
+///
+/// * `*` matches any numeric segment, like `2` or `10`.
+/// * A number matches the same number, ignoring leading zeros, so `1` matches `01`.
+/// * `[#lo-hi]` matches any number from `lo` to `hi`, inclusive, compared numerically.
+/// * Anything else is matched with [`stringmatch`], so `rc*` matches `rc1`.
+///
+/// ```
+/// # use moenster::stringmatch_version;
+/// assert!(stringmatch_version("1.*", "1.10"));
+/// assert!(stringmatch_version("1.[#2-12].*", "1.10.3"));
+/// assert!(!stringmatch_version("1.*", "1.2.3"));
+/// ```
+pub fn stringmatch_version(pattern: &str, version: &str) -> bool {
+    let mut patterns = pattern.split('.');
+    let mut segments = version.split('.');
+    loop {
+        match (patterns.next(), segments.next()) {
+            (None, None) => return true,
+            (Some(pattern), Some(segment)) => {
+                if !segment_matches(pattern, segment) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Match a dotted version number like [`stringmatch_version`], validating the pattern first.
+///
+/// Returns [`PatternError::NumericBoundOutOfRange`] if a bound of a numeric range is too large
+/// for a `u64`, where [`stringmatch_version`] treats the range as matching nothing.
+///
+/// ```
+/// # use moenster::{try_stringmatch_version, PatternError};
+/// assert_eq!(try_stringmatch_version("1.[#2-12]", "1.10"), Ok(true));
+/// assert_eq!(
+///     try_stringmatch_version("1.[#0-99999999999999999999]", "1.10"),
+///     Err(PatternError::NumericBoundOutOfRange { position: 6 })
+/// );
+/// ```
+pub fn try_stringmatch_version(pattern: &str, version: &str) -> Result<bool, PatternError> {
+    let mut offset = 0;
+    for segment in pattern.split('.') {
+        if let Some(range) = segment.strip_prefix("[#").and_then(|p| p.strip_suffix(']')) {
+            let mut position = offset + 2;
+            for bound in range.splitn(2, '-') {
+                if is_number(bound) && bound.parse::<u64>().is_err() {
+                    return Err(PatternError::NumericBoundOutOfRange { position });
+                }
+                position += bound.len() + 1;
+            }
+        }
+        offset += segment.len() + 1;
+    }
+    Ok(stringmatch_version(pattern, version))
+}
+
+fn segment_matches(pattern: &str, segment: &str) -> bool {
+    if pattern == "*" {
+        return is_number(segment);
+    }
+
+    if is_number(pattern) {
+        return is_number(segment) && strip_zeros(pattern) == strip_zeros(segment);
+    }
+
+    if let Some(range) = pattern.strip_prefix("[#").and_then(|p| p.strip_suffix(']')) {
+        // Numbers too large for a `u64` never match.
+        let mut bounds = range.splitn(2, '-').map(|b| b.parse::<u64>().ok());
+        return match (bounds.next().flatten(), bounds.next().flatten()) {
+            (Some(lo), Some(hi)) if is_number(segment) => segment
+                .parse::<u64>()
+                .map(|n| lo <= n && n <= hi)
+                .unwrap_or(false),
+            _ => false,
+        };
+    }
+
+    stringmatch(pattern, segment)
+}
+
+fn is_number(segment: &str) -> bool {
+    !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn strip_zeros(number: &str) -> &str {
+    let stripped = number.trim_start_matches('0');
+    if stripped.is_empty() {
+        "0"
+    } else {
+        stripped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_segments() {
+        assert!(stringmatch_version("1.*", "1.2"));
+        assert!(stringmatch_version("1.*", "1.10"));
+        assert!(stringmatch_version("1.*.*", "1.2.3"));
+        assert!(stringmatch_version("1.10", "1.010"));
+        assert!(!stringmatch_version("1.*", "1.2.3"));
+        assert!(!stringmatch_version("1.*", "1"));
+        assert!(!stringmatch_version("1.*", "1.x"));
+        assert!(!stringmatch_version("1.*", "1."));
+        assert!(!stringmatch_version("1.1", "1.10"));
+    }
+
+    #[test]
+    fn numeric_ranges() {
+        assert!(stringmatch_version("1.[#2-5].*", "1.3.0"));
+        assert!(stringmatch_version("1.[#9-12]", "1.10"));
+        assert!(stringmatch_version("1.[#9-12]", "1.9"));
+        assert!(!stringmatch_version("1.[#2-5]", "1.10"));
+        assert!(!stringmatch_version("1.[#9-12]", "1.100"));
+        assert!(!stringmatch_version("[#0-99999999999999999999]", "1"));
+    }
+
+    #[test]
+    fn out_of_range_bounds() {
+        assert_eq!(
+            try_stringmatch_version("[#0-99999999999999999999]", "1"),
+            Err(PatternError::NumericBoundOutOfRange { position: 4 })
+        );
+        assert_eq!(
+            try_stringmatch_version("1.[#18446744073709551616-2].*", "1.2.3"),
+            Err(PatternError::NumericBoundOutOfRange { position: 4 })
+        );
+        // `u64::MAX` itself still fits.
+        assert_eq!(
+            try_stringmatch_version("[#1-18446744073709551615]", "18446744073709551615"),
+            Ok(true)
+        );
+        assert_eq!(try_stringmatch_version("1.[#2-5].*", "1.3.0"), Ok(true));
+        assert_eq!(try_stringmatch_version("1.[#2-5]", "1.10"), Ok(false));
+    }
+
+    #[test]
+    fn textual_segments() {
+        assert!(stringmatch_version("1.2.rc*", "1.2.rc1"));
+        assert!(!stringmatch_version("1.2.rc*", "1.2.beta"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Walking a directory tree for paths matching a pattern.
+
+use std::fs::{self, ReadDir};
+use std::path::{Path, PathBuf};
+
+use crate::stringmatch_path;
+
+/// Walk the directory tree below `root` and yield every path matching the pattern.
+///
+/// The pattern is matched in path mode (see [`stringmatch_path`]) against the path relative to
+/// `root`, using `/` as the separator on all platforms. Both files and directories are yielded;
+/// the yielded paths are prefixed with `root`. Directories also match with a trailing `/`, so
+/// a pattern like `**/target/` only yields directories.
+///
+/// The tree is walked lazily, one directory at a time.
+/// Symbolic links are not followed.
+/// Entries that can't be read and paths that are not valid UTF-8 are skipped.
+///
+/// ```no_run
+/// # use moenster::walk_matching;
+/// for path in walk_matching("src", "**/*.rs") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn walk_matching<P: AsRef<Path>>(root: P, pattern: &str) -> impl Iterator<Item = PathBuf> {
+    let root = root.as_ref().to_path_buf();
+    let stack = fs::read_dir(&root)
+        .map(|dir| vec![(String::new(), dir)])
+        .unwrap_or_default();
+
+    WalkMatching {
+        root,
+        pattern: pattern.to_string(),
+        stack,
+    }
+}
+
+struct WalkMatching {
+    root: PathBuf,
+    pattern: String,
+    /// Directories currently being read, with their path relative to the root.
+    stack: Vec<(String, ReadDir)>,
+}
+
+impl Iterator for WalkMatching {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        loop {
+            let (prefix, dir) = self.stack.last_mut()?;
+            let entry = match dir.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(_)) => continue,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let name = entry.file_name();
+            let name = match name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            let relative = format!("{}{}", prefix, name);
+
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_dir {
+                if let Ok(dir) = fs::read_dir(entry.path()) {
+                    self.stack.push((format!("{}/", relative), dir));
+                }
+            }
+
+            let matched = stringmatch_path(&self.pattern, &relative)
+                || (is_dir && stringmatch_path(&self.pattern, &format!("{}/", relative)));
+            if matched {
+                return Some(self.root.join(relative));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_fixture() {
+        let root = std::env::temp_dir().join(format!("moenster-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        for file in &[
+            "README.md",
+            "src/lib.rs",
+            "src/bin/main.rs",
+            "tests/smoke.rs",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let mut found: Vec<_> = walk_matching(&root, "src/**/*.rs").collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![root.join("src/bin/main.rs"), root.join("src/lib.rs")]
+        );
+
+        let mut found: Vec<_> = walk_matching(&root, "*").collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![root.join("README.md"), root.join("src"), root.join("tests")]
+        );
+
+        let found: Vec<_> = walk_matching(&root, "*/").collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|path| path.is_dir()));
+
+        let mut found: Vec<_> = walk_matching(&root, "src/**/").collect();
+        found.sort();
+        assert_eq!(found, vec![root.join("src/bin")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}